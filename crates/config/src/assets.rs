@@ -5,6 +5,36 @@
 #[cfg_attr(not(feature = "unstable"), non_exhaustive)]
 pub struct Assets {
     pub sass: Sass,
+    pub css: Css,
+    /// Files built by concatenating other files, by name
+    pub bundles: std::collections::BTreeMap<liquid_core::model::KString, Bundle>,
+}
+
+/// A single file concatenated from others, e.g. `js/site.js` from `_js/vendor/*.js` and
+/// `_js/app.js`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct Bundle {
+    /// Where the bundle is written, relative to the destination
+    pub output: crate::RelPath,
+    /// Gitignore-style globs, relative to the source, whose files are included in order
+    pub inputs: Vec<liquid_core::model::KString>,
+}
+
+/// Post-processing for `.css` files and compiled Sass
+#[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct Css {
+    /// Browsers to lower nesting, add prefixes and transform syntax for, as a
+    /// [browserslist](https://browsersl.ist) query, e.g. `"chrome >= 100, safari >= 15.4"`
+    pub targets: Option<liquid_core::model::KString>,
+    /// Inline `@import`ed stylesheets
+    pub bundle: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -15,6 +45,8 @@ pub struct Assets {
 pub struct Sass {
     #[serde(skip)]
     pub import_dir: &'static str,
+    /// Extra directories to resolve `@import` and `@use` from, after `_sass`
+    pub load_paths: Vec<crate::RelPath>,
     pub style: SassOutputStyle,
 }
 
@@ -22,6 +54,7 @@ impl Default for Sass {
     fn default() -> Self {
         Self {
             import_dir: "_sass",
+            load_paths: Vec::new(),
             style: Default::default(),
         }
     }
@@ -21,15 +21,34 @@ pub struct Config {
     pub pages: PageCollection,
     pub posts: PostCollection,
     pub site: Site,
+    /// Extensions of files rendered as pages; notebooks are opt-in by adding `ipynb`
     pub template_extensions: Vec<liquid_core::model::KString>,
     pub ignore: Vec<liquid_core::model::KString>,
     pub syntax_highlight: SyntaxHighlight,
+    pub markdown: Markdown,
     #[serde(skip)]
     pub layouts_dir: &'static str,
     #[serde(skip)]
     pub includes_dir: &'static str,
+    #[serde(skip)]
+    pub shortcodes_dir: &'static str,
     pub assets: Assets,
     pub minify: Minify,
+    pub csp: Csp,
+    pub compress: Compress,
+}
+
+/// Only extensions with a renderer compiled in, so a `.dj` or `.org` file isn't templated as raw text
+fn default_template_extensions() -> Vec<liquid_core::model::KString> {
+    let mut extensions = vec!["md".into()];
+    if cfg!(feature = "djot") {
+        extensions.push("dj".into());
+    }
+    if cfg!(feature = "org") {
+        extensions.push("org".into());
+    }
+    extensions.extend(["wiki".into(), "liquid".into()]);
+    extensions
 }
 
 impl Default for Config {
@@ -44,13 +63,17 @@ impl Default for Config {
             pages: Default::default(),
             posts: Default::default(),
             site: Default::default(),
-            template_extensions: vec!["md".into(), "wiki".into(), "liquid".into()],
+            template_extensions: default_template_extensions(),
             ignore: Default::default(),
             syntax_highlight: SyntaxHighlight::default(),
+            markdown: Markdown::default(),
             layouts_dir: "_layouts",
             includes_dir: "_includes",
+            shortcodes_dir: "_shortcodes",
             assets: Assets::default(),
             minify: Minify::default(),
+            csp: Csp::default(),
+            compress: Compress::default(),
         }
     }
 }
@@ -126,6 +149,14 @@ impl fmt::Display for Config {
 pub struct SyntaxHighlight {
     pub theme: liquid_core::model::KString,
     pub enabled: bool,
+    pub mode: HighlightMode,
+    /// Write the CSS for `classes` mode as part of the build
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stylesheet: Option<HighlightStylesheet>,
+    /// Project `.sublime-syntax` files, added to the built-in syntaxes
+    pub syntaxes_dir: RelPath,
+    /// Project `.tmTheme` files, added to the built-in themes under their file name
+    pub themes_dir: RelPath,
 }
 
 impl Default for SyntaxHighlight {
@@ -133,10 +164,53 @@ impl Default for SyntaxHighlight {
         Self {
             theme: "base16-ocean.dark".into(),
             enabled: true,
+            mode: Default::default(),
+            stylesheet: None,
+            syntaxes_dir: "_syntaxes".try_into().unwrap(),
+            themes_dir: "_themes".try_into().unwrap(),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+#[derive(Default)]
+pub enum HighlightMode {
+    /// Colour each token with a `style` attribute from `theme`
+    #[default]
+    Inline,
+    /// Mark each token with CSS classes, leaving colours to a stylesheet
+    Classes,
+    #[cfg(not(feature = "unstable"))]
+    #[doc(hidden)]
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct HighlightStylesheet {
+    /// Where to write the stylesheet, relative to the destination
+    pub path: RelPath,
+    /// Themes to include, in order
+    pub themes: Vec<StylesheetTheme>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct StylesheetTheme {
+    pub theme: liquid_core::model::KString,
+    /// Only apply the theme within this media query, e.g. `(prefers-color-scheme: dark)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media: Option<liquid_core::model::KString>,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 #[serde(rename_all = "snake_case")]
@@ -148,6 +222,43 @@ pub struct Minify {
     pub js: bool,
 }
 
+/// Support for a `Content-Security-Policy` without `unsafe-inline`
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct Csp {
+    /// Hash each page's inline `<script>` and `<style>` blocks into `page.csp`
+    pub hashes: bool,
+    /// Also write the hashes of every page, as JSON with `script_src` and `style_src` sources,
+    /// e.g. `csp.json`; implies `hashes`
+    pub hashes_file: Option<RelPath>,
+}
+
+/// Write compressed siblings of text outputs, e.g. `index.html.gz`, for servers that prefer them
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct Compress {
+    pub gzip: bool,
+    pub brotli: bool,
+    /// Files smaller than this many bytes are left uncompressed
+    pub min_size: u64,
+}
+
+impl Default for Compress {
+    fn default() -> Self {
+        Self {
+            gzip: false,
+            brotli: false,
+            min_size: 1024,
+        }
+    }
+}
+
 fn find_project_file<P: Into<path::PathBuf>>(dir: P, name: &str) -> Option<path::PathBuf> {
     find_project_file_internal(dir.into(), name)
 }
@@ -40,6 +40,8 @@ pub struct Frontmatter {
     pub data: liquid_core::Object,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pagination: Option<Pagination>,
+    #[serde(skip_serializing_if = "Markdown::is_empty")]
+    pub markdown: Markdown,
     // Controlled by where the file is found.  We might allow control over the type at a later
     // point but we need to first define those semantics.
     #[serde(skip)]
@@ -63,12 +65,6 @@ impl Frontmatter {
                 self.templated.get_or_insert(false);
             }
 
-            let format = match split_name.1 {
-                Some("md") => SourceFormat::Markdown,
-                _ => SourceFormat::Raw,
-            };
-            self.format.get_or_insert(format);
-
             while split_name.1.is_some() {
                 split_name = path::split_ext(split_name.0);
             }
@@ -111,6 +107,7 @@ impl Frontmatter {
             collection,
             data,
             pagination,
+            markdown,
         } = self;
         Self {
             permalink: permalink.or_else(|| other.permalink.clone()),
@@ -130,6 +127,7 @@ impl Frontmatter {
             collection: collection.or_else(|| other.collection.clone()),
             data: merge_objects(data, &other.data),
             pagination: merge_pagination(pagination, &other.pagination),
+            markdown: markdown.merge(&other.markdown),
         }
     }
 }
@@ -274,6 +272,7 @@ impl AsRef<str> for ExplicitPermalink {
     }
 }
 
+/// How to render a document, instead of going by its file extension
 #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "preview_unstable", serde(rename_all = "snake_case"))]
 #[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
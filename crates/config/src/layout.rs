@@ -0,0 +1,118 @@
+use crate::Result;
+use crate::Status;
+use liquid_core::ValueView as _;
+
+/// A template in `_layouts`, with optional frontmatter
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct Layout {
+    front: liquid_core::Object,
+    parent: Option<liquid_core::model::KString>,
+    content: liquid_core::model::KString,
+}
+
+impl Layout {
+    pub fn new(front: liquid_core::Object, content: liquid_core::model::KString) -> Self {
+        let parent = front
+            .get("layout")
+            .filter(|layout| !layout.is_nil())
+            .map(|layout| layout.to_kstr().into_owned());
+        Self {
+            front,
+            parent,
+            content,
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let (front, content) = split_layout(content);
+        let front = front
+            .map(parse_frontmatter)
+            .transpose()?
+            .unwrap_or_default();
+        let content = liquid_core::model::KString::from_ref(content);
+        Ok(Self::new(front, content))
+    }
+
+    /// The layout this layout is rendered into
+    pub fn layout(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    pub fn front(&self) -> &liquid_core::Object {
+        &self.front
+    }
+
+    pub fn content(&self) -> &str {
+        self.content.as_str()
+    }
+
+    pub fn into_parts(self) -> (liquid_core::Object, liquid_core::model::KString) {
+        let Self { front, content, .. } = self;
+        (front, content)
+    }
+}
+
+fn parse_frontmatter(front: &str) -> Result<liquid_core::Object> {
+    let front: liquid_core::Object = serde_yaml::from_str(front)
+        .map_err(|e| Status::new("Failed to parse layout frontmatter").with_source(e))?;
+    Ok(front)
+}
+
+/// Layouts only support frontmatter surrounded, above and below, with `---`
+fn split_layout(content: &str) -> (Option<&str>, &str) {
+    static FRONT_MATTER: std::sync::LazyLock<regex::Regex> = std::sync::LazyLock::new(|| {
+        regex::RegexBuilder::new(r"\A---\s*\r?\n([\s\S]*?\n)?---\s*\r?\n")
+            .build()
+            .unwrap()
+    });
+
+    if let Some(captures) = FRONT_MATTER.captures(content) {
+        let front_split = captures.get(1).map(|m| m.as_str()).unwrap_or_default();
+        let content_split = &content[captures.get(0).unwrap().end()..];
+
+        if front_split.is_empty() {
+            (None, content_split)
+        } else {
+            (Some(front_split), content_split)
+        }
+    } else {
+        (None, content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_layout_no_front_matter() {
+        let input = "<p>\n---\n</p>";
+        let (front, content) = split_layout(input);
+        assert!(front.is_none());
+        assert_eq!(content, input);
+    }
+
+    #[test]
+    fn split_layout_front_matter_and_body() {
+        let input = "---\nlayout: default.liquid\n---\n<p>\n---\n</p>";
+        let (front, content) = split_layout(input);
+        assert_eq!(front.unwrap(), "layout: default.liquid\n");
+        assert_eq!(content, "<p>\n---\n</p>");
+    }
+
+    #[test]
+    fn parse_parent_layout() {
+        let layout = Layout::parse("---\nlayout: default.liquid\ntitle: Post\n---\nbody").unwrap();
+        assert_eq!(layout.layout(), Some("default.liquid"));
+        assert_eq!(layout.front().len(), 2);
+        assert_eq!(layout.content(), "body");
+    }
+
+    #[test]
+    fn parse_without_front() {
+        let layout = Layout::parse("body").unwrap();
+        assert_eq!(layout.layout(), None);
+        assert!(layout.front().is_empty());
+        assert_eq!(layout.content(), "body");
+    }
+}
@@ -8,6 +8,8 @@ mod collection;
 mod config;
 mod document;
 mod frontmatter;
+mod layout;
+mod markdown;
 mod pagination;
 mod site;
 
@@ -18,6 +20,8 @@ pub use self::collection::*;
 pub use self::config::*;
 pub use self::document::*;
 pub use self::frontmatter::*;
+pub use self::layout::*;
+pub use self::markdown::*;
 pub use self::pagination::*;
 pub use self::site::*;
 pub use liquid_core::model::DateTime;
@@ -0,0 +1,122 @@
+/// Markdown extensions, unset values fall back to the site-wide setting and then the default
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct Markdown {
+    /// Default: `true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub footnotes: Option<bool>,
+    /// Default: `true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tables: Option<bool>,
+    /// Default: `true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    /// Default: `true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tasklists: Option<bool>,
+    /// Curly quotes, dashes and ellipses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smart_punctuation: Option<bool>,
+    /// `# Heading {#id .class}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_attributes: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definition_lists: Option<bool>,
+    /// GitHub-style alerts / callouts, e.g. `> [!NOTE]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<bool>,
+    /// `$inline$` and `$$display$$` math, converted to `MathML`; requires cobalt built with the `math` feature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub math: Option<bool>,
+    /// `^superscript^`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub superscript: Option<bool>,
+    /// `~subscript~`, with strikethrough requiring `~~`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscript: Option<bool>,
+    /// `[[Page]]` and `[[page|text]]` links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wikilinks: Option<bool>,
+    /// Give each heading a unique `id` and list them in `page.toc`.  Default: `heading_anchors`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_ids: Option<bool>,
+    /// Add a self-link to each heading.  Default: `false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_anchors: Option<bool>,
+    /// Shallowest heading included in `page.toc`.  Default: `1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toc_min_level: Option<u8>,
+    /// Deepest heading included in `page.toc`.  Default: `6`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toc_max_level: Option<u8>,
+}
+
+impl Markdown {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn merge(self, other: &Self) -> Self {
+        let Self {
+            footnotes,
+            tables,
+            strikethrough,
+            tasklists,
+            smart_punctuation,
+            heading_attributes,
+            definition_lists,
+            alerts,
+            math,
+            superscript,
+            subscript,
+            wikilinks,
+            heading_ids,
+            heading_anchors,
+            toc_min_level,
+            toc_max_level,
+        } = self;
+        Self {
+            footnotes: footnotes.or(other.footnotes),
+            tables: tables.or(other.tables),
+            strikethrough: strikethrough.or(other.strikethrough),
+            tasklists: tasklists.or(other.tasklists),
+            smart_punctuation: smart_punctuation.or(other.smart_punctuation),
+            heading_attributes: heading_attributes.or(other.heading_attributes),
+            definition_lists: definition_lists.or(other.definition_lists),
+            alerts: alerts.or(other.alerts),
+            math: math.or(other.math),
+            superscript: superscript.or(other.superscript),
+            subscript: subscript.or(other.subscript),
+            wikilinks: wikilinks.or(other.wikilinks),
+            heading_ids: heading_ids.or(other.heading_ids),
+            heading_anchors: heading_anchors.or(other.heading_anchors),
+            toc_min_level: toc_min_level.or(other.toc_min_level),
+            toc_max_level: toc_max_level.or(other.toc_max_level),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_prefers_self() {
+        let site = Markdown {
+            tables: Some(false),
+            math: Some(true),
+            ..Default::default()
+        };
+        let page = Markdown {
+            math: Some(false),
+            ..Default::default()
+        };
+        let merged = page.merge(&site);
+        assert_eq!(merged.tables, Some(false));
+        assert_eq!(merged.math, Some(false));
+        assert_eq!(merged.footnotes, None);
+    }
+}
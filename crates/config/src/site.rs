@@ -8,6 +8,7 @@ pub struct Site {
     pub description: Option<liquid_core::model::KString>,
     pub base_url: Option<liquid_core::model::KString>,
     pub sitemap: Option<crate::RelPath>,
+    pub robots: Option<Robots>,
     pub data: Option<liquid_core::Object>,
     #[serde(skip)]
     pub data_dir: &'static str,
@@ -20,8 +21,39 @@ impl Default for Site {
             description: Default::default(),
             base_url: Default::default(),
             sitemap: Default::default(),
+            robots: Default::default(),
             data: Default::default(),
             data_dir: "_data",
         }
     }
 }
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct Robots {
+    pub rules: Vec<RobotsRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "unstable", serde(deny_unknown_fields))]
+#[cfg_attr(not(feature = "unstable"), non_exhaustive)]
+pub struct RobotsRule {
+    pub user_agent: liquid_core::model::KString,
+    pub allow: Vec<liquid_core::model::KString>,
+    pub disallow: Vec<liquid_core::model::KString>,
+}
+
+impl Default for RobotsRule {
+    fn default() -> Self {
+        Self {
+            user_agent: "*".into(),
+            allow: Default::default(),
+            disallow: Default::default(),
+        }
+    }
+}
@@ -40,6 +40,14 @@ impl ConfigArgs {
         Ok(config)
     }
 
+    /// The file `config` was loaded from, or would be
+    #[cfg(feature = "watch")]
+    pub(crate) fn config_file(&self, config: &cobalt_config::Config) -> path::PathBuf {
+        self.config
+            .clone()
+            .unwrap_or_else(|| config.root.join("_cobalt.yml"))
+    }
+
     pub(crate) fn drafts(&self) -> Option<bool> {
         resolve_bool_arg(self.drafts, self.no_drafts)
     }
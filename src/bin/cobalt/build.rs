@@ -11,12 +11,51 @@ pub(crate) struct BuildArgs {
     #[arg(short, long, value_name = "DIR", help_heading = "Config")]
     destination: Option<std::path::PathBuf>,
 
+    /// Rebuild on change
+    #[cfg(feature = "watch")]
+    #[arg(long)]
+    watch: bool,
+
     #[command(flatten, next_help_heading = "Config")]
     pub(crate) config: args::ConfigArgs,
 }
 
 impl BuildArgs {
     pub(crate) fn run(&self) -> Result<()> {
+        let config = self.load_config()?;
+
+        #[cfg(feature = "watch")]
+        if self.watch {
+            let mut layouts = cobalt::LayoutCache::new();
+            build_with_layouts(
+                cobalt::cobalt_model::Config::from_config(config.clone())?,
+                &mut layouts,
+            )?;
+            log::info!("Build successful");
+
+            let config_file = self.config.config_file(&config);
+            return crate::watch::watch(
+                config,
+                &config_file,
+                || self.load_config(),
+                &mut layouts,
+                |result, _changed| {
+                    if result.is_ok() {
+                        log::info!("Build successful");
+                    }
+                },
+            );
+        }
+
+        let config = cobalt::cobalt_model::Config::from_config(config)?;
+
+        build(config)?;
+        log::info!("Build successful");
+
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<cobalt_config::Config> {
         let mut config = self.config.load_config()?;
         config.abs_dest = self
             .destination
@@ -26,23 +65,25 @@ impl BuildArgs {
                 dunce::canonicalize(d)
             })
             .transpose()?;
-
-        let config = cobalt::cobalt_model::Config::from_config(config)?;
-
-        build(config)?;
-        log::info!("Build successful");
-
-        Ok(())
+        Ok(config)
     }
 }
 
 pub(crate) fn build(config: cobalt::Config) -> Result<()> {
+    build_with_layouts(config, &mut cobalt::LayoutCache::new())
+}
+
+/// Build, reusing layouts compiled by earlier builds
+pub(crate) fn build_with_layouts(
+    config: cobalt::Config,
+    layouts: &mut cobalt::LayoutCache,
+) -> Result<()> {
     log::info!(
         "Building from `{}` into `{}`",
         config.source.display(),
         config.destination.display()
     );
-    cobalt::build(config)?;
+    cobalt::build_with_layouts(config, layouts)?;
 
     Ok(())
 }
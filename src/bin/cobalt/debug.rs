@@ -77,7 +77,9 @@ impl DebugCommands {
             Self::Highlight(HighlightCommands::SaveThemeCss { config, name, path }) => {
                 let config = config.load_config()?;
                 let config = cobalt::cobalt_model::Config::from_config(config)?;
-                if name == engarde::Syntax::css_theme_name() || !config.syntax.has_theme(name) {
+                if name == cobalt::SyntaxHighlight::css_theme_name()
+                    || !config.syntax.has_theme(name)
+                {
                     return Err(anyhow::anyhow!("Unknown theme: {name}"));
                 }
 
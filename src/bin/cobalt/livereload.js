@@ -0,0 +1,20 @@
+// Added to pages by `cobalt serve`; reloads them after each rebuild
+(() => {
+  const events = new EventSource("/_cobalt/events");
+  events.onmessage = (event) => {
+    const links = [...document.querySelectorAll('link[rel="stylesheet"]')].filter(
+      (link) => new URL(link.href, location.href).origin === location.origin,
+    );
+    // A changed stylesheet no longer matches its `integrity`, so only a reload picks it up
+    if (event.data !== "css" || links.some((link) => link.hasAttribute("integrity"))) {
+      location.reload();
+      return;
+    }
+    // Swap stylesheets in place, keeping scroll position and page state
+    for (const link of links) {
+      const url = new URL(link.href, location.href);
+      url.searchParams.set("cobalt-reload", Date.now());
+      link.href = url.href;
+    }
+  };
+})();
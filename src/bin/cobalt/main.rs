@@ -7,6 +7,10 @@ mod error;
 mod new;
 #[cfg(feature = "serve")]
 mod serve;
+#[cfg(feature = "serve")]
+mod server;
+#[cfg(feature = "watch")]
+mod watch;
 
 use std::alloc;
 
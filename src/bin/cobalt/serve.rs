@@ -1,15 +1,13 @@
-use std::path;
 use std::process;
-use std::sync::mpsc::channel;
 use std::thread;
 
-use anyhow::Context as _;
 use cobalt::cobalt_model;
-use notify::Watcher as _;
 
 use crate::args;
 use crate::build;
 use crate::error::Result;
+use crate::server;
+use crate::watch;
 
 /// Build, serve, and watch the project at the source dir
 #[derive(Clone, Debug, PartialEq, Eq, clap::Args)]
@@ -26,6 +24,10 @@ pub(crate) struct ServeArgs {
     #[arg(short = 'P', long, value_name = "NUM")]
     pub(crate) port: Option<u16>,
 
+    /// Serve the site under this path, like it is deployed
+    #[arg(long, value_name = "PATH", default_value = "/")]
+    pub(crate) base_path: String,
+
     /// Disable rebuilding on change
     #[arg(long)]
     pub(crate) no_watch: bool,
@@ -38,60 +40,81 @@ impl ServeArgs {
     pub(crate) fn run(&self) -> Result<()> {
         let dest = tempfile::tempdir()?;
 
-        let mut server = file_serve::ServerBuilder::new(dest.path());
+        let mut server = server::ServerBuilder::new(dest.path());
         server.hostname(&self.host);
         if let Some(port) = self.port {
             server.port(port);
         }
+        server.base_path(&self.base_path);
+        server.live_reload(!self.no_watch);
         let server = server.build();
 
-        let mut config = self.config.load_config()?;
-        log::debug!("Overriding config `site.base_url` with `/`");
-        let host = format!("http://{}/", server.addr());
-        config.site.base_url = Some(host.into());
-        let mut config = cobalt_model::Config::from_config(config)?;
-        log::debug!(
-            "Overriding config `destination` with `{}`",
-            dest.path().display()
-        );
-        config.destination = dest.path().to_owned();
-        build::build(config.clone())?;
+        let load_config = || -> Result<cobalt_config::Config> {
+            let mut config = self.config.load_config()?;
+            let url = server.url();
+            log::debug!("Overriding config `site.base_url` with `{url}`");
+            config.site.base_url = Some(url.into());
+            log::debug!(
+                "Overriding config `destination` with `{}`",
+                dest.path().display()
+            );
+            config.abs_dest = Some(dest.path().to_owned());
+            Ok(config)
+        };
+        let config = load_config()?;
+        let mut layouts = cobalt::LayoutCache::new();
+        build::build_with_layouts(
+            cobalt_model::Config::from_config(config.clone())?,
+            &mut layouts,
+        )?;
 
         if self.open {
-            let url = format!("http://{}", server.addr());
-            open_browser(url)?;
+            open_browser(server.url())?;
         }
 
         if self.no_watch {
             serve(&server)?;
-
-            dest.close()?;
         } else {
-            log::info!("Watching {} for changes", config.source.display());
-            thread::spawn(move || {
-                let e = serve(&server);
-                if let Some(e) = e.err() {
-                    log::error!("{e}");
-                }
-                process::exit(1)
-            });
-
-            watch(&config)?;
+            let reloader = server.reloader();
+            thread::scope(|scope| -> Result<()> {
+                scope.spawn(|| {
+                    let e = serve(&server);
+                    if let Some(e) = e.err() {
+                        log::error!("{e}");
+                    }
+                    process::exit(1)
+                });
+
+                let config_file = self.config.config_file(&config);
+                watch::watch(
+                    config,
+                    &config_file,
+                    load_config,
+                    &mut layouts,
+                    |result, changed| match result {
+                        Ok(()) => reloader.notify(server::Change::from_paths(
+                            changed.iter().map(|path| path.as_path()),
+                        )),
+                        Err(fail) => reloader.fail(format!("{fail:?}")),
+                    },
+                )
+            })?;
         }
+        dest.close()?;
 
         Ok(())
     }
 }
 
-fn serve(server: &file_serve::Server) -> Result<()> {
+fn serve(server: &server::Server) -> Result<()> {
     log::info!(
         "Serving {} through static file server",
-        server.source().display()
+        server.root().display()
     );
-    log::info!("Server Listening on http://{}", server.addr());
+    log::info!("Server Listening on {}", server.url());
     log::info!("Ctrl-c to stop the server");
 
-    Ok(server.serve()?)
+    server.serve()
 }
 
 fn open_browser(url: String) -> Result<()> {
@@ -101,64 +124,3 @@ fn open_browser(url: String) -> Result<()> {
     }
     Ok(())
 }
-
-fn watch(config: &cobalt_model::Config) -> Result<()> {
-    // canonicalize is to ensure there is no question that `watcher`s paths come back safe for
-    // Files::includes_file
-    let source = dunce::canonicalize(path::Path::new(&config.source)).with_context(|| {
-        anyhow::format_err!("Failed in processing source `{}`", config.source.display())
-    })?;
-
-    // Also canonicalize the destination folder. In particular for Windows, notify-rs
-    // generates the absolute path by prepending the above source path.
-    // On Windows canonicalize() adds a \\?\ to the start of the path.
-    let destination = dunce::canonicalize(&config.destination).with_context(|| {
-        anyhow::format_err!(
-            "Failed to canonicalize destination folder `{}`",
-            config.destination.display()
-        )
-    })?;
-
-    let (tx, rx) = channel();
-    let mut watcher =
-        notify::recommended_watcher(tx).with_context(|| anyhow::format_err!("Notify error"))?;
-    watcher
-        .watch(&source, notify::RecursiveMode::Recursive)
-        .with_context(|| anyhow::format_err!("Notify error"))?;
-    log::info!("Watching {} for changes", config.source.display());
-
-    for event in rx {
-        let event = event.with_context(|| anyhow::format_err!("Notify error"))?;
-        let event_paths = match event.kind {
-            notify::EventKind::Create(_)
-            | notify::EventKind::Modify(_)
-            | notify::EventKind::Remove(_) => {
-                log::trace!("Noticed {:?} for {:#?}", event.kind, event.paths);
-                &event.paths
-            }
-            _ => {
-                continue;
-            }
-        };
-        let rebuild = event_paths.iter().any(|event_path| {
-            // Be as broad as possible in what can cause a rebuild to
-            // ensure we don't miss anything (normal file walks will miss
-            // `_layouts`, etc).
-            if event_path.starts_with(&destination) {
-                log::trace!("Ignored file changed {event:?}");
-                false
-            } else {
-                log::debug!("Page changed {event:?}");
-                true
-            }
-        });
-        if rebuild {
-            let result = build::build(config.clone());
-            if let Err(fail) = result {
-                log::error!("build failed\n{fail:?}");
-            }
-        }
-    }
-
-    Ok(())
-}
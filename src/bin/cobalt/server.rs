@@ -0,0 +1,550 @@
+//! Static file server for `cobalt serve`, with live reload
+
+use std::io::Write as _;
+use std::path;
+use std::str::FromStr as _;
+use std::sync::{Arc, Mutex, PoisonError, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::Result;
+
+/// Where pages load the live reload script from
+const SCRIPT_PATH: &str = "/_cobalt/livereload.js";
+/// Where the live reload script listens for rebuilds, as server-sent events
+const EVENTS_PATH: &str = "/_cobalt/events";
+
+const SCRIPT: &str = include_str!("livereload.js");
+
+/// How often idle event streams are pinged, so closed connections are noticed
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// What browsers need to do after a rebuild
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Change {
+    /// Only stylesheets changed, swap them in place
+    Css,
+    /// Reload the page
+    Page,
+}
+
+impl Change {
+    /// Classify a rebuild by the source files that triggered it
+    pub(crate) fn from_paths<'p>(paths: impl IntoIterator<Item = &'p path::Path>) -> Self {
+        let mut paths = paths.into_iter().peekable();
+        let is_css = paths.peek().is_some()
+            && paths.all(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| matches!(ext, "css" | "scss" | "sass"))
+            });
+        if is_css { Self::Css } else { Self::Page }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Css => "css",
+            Self::Page => "reload",
+        }
+    }
+}
+
+/// Tells connected browsers about rebuilds
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Reloader {
+    clients: Arc<Mutex<Vec<mpsc::Sender<Change>>>>,
+    failure: Arc<Mutex<Option<String>>>,
+}
+
+impl Reloader {
+    /// The site was rebuilt
+    pub(crate) fn notify(&self, change: Change) {
+        let recovered = self
+            .failure
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .is_some();
+        // Browsers are showing the error page, not the site's stylesheets
+        let change = if recovered { Change::Page } else { change };
+        self.send(change);
+    }
+
+    /// Show `failure` in place of the site until the next successful rebuild
+    pub(crate) fn fail(&self, failure: String) {
+        *self.failure.lock().unwrap_or_else(PoisonError::into_inner) = Some(failure);
+        self.send(Change::Page);
+    }
+
+    fn failure(&self) -> Option<String> {
+        self.failure
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn send(&self, change: Change) {
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        clients.retain(|client| client.send(change).is_ok());
+        log::debug!("Sent `{}` to {} browsers", change.as_str(), clients.len());
+    }
+
+    fn subscribe(&self) -> mpsc::Receiver<Change> {
+        let (tx, rx) = mpsc::channel();
+        self.clients
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(tx);
+        rx
+    }
+}
+
+/// Custom server settings
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ServerBuilder {
+    root: path::PathBuf,
+    hostname: Option<String>,
+    port: Option<u16>,
+    base_path: String,
+    live_reload: bool,
+}
+
+impl ServerBuilder {
+    pub(crate) fn new(root: impl Into<path::PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            hostname: None,
+            port: None,
+            base_path: String::new(),
+            live_reload: false,
+        }
+    }
+
+    /// Override the hostname
+    pub(crate) fn hostname(&mut self, hostname: impl Into<String>) -> &mut Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Override the port
+    ///
+    /// By default, the first available port is selected.
+    pub(crate) fn port(&mut self, port: u16) -> &mut Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Mount the site under a prefix, e.g. `/docs`
+    pub(crate) fn base_path(&mut self, base_path: &str) -> &mut Self {
+        let base_path = base_path.trim_matches('/');
+        self.base_path = if base_path.is_empty() {
+            String::new()
+        } else {
+            format!("/{base_path}")
+        };
+        self
+    }
+
+    /// Add the live reload script to served pages
+    pub(crate) fn live_reload(&mut self, yes: bool) -> &mut Self {
+        self.live_reload = yes;
+        self
+    }
+
+    pub(crate) fn build(&self) -> Server {
+        let hostname = self.hostname.as_deref().unwrap_or("localhost");
+        let port = self
+            .port
+            .or_else(|| available_port(hostname))
+            // Just have `serve` error out
+            .unwrap_or(3000);
+
+        Server {
+            root: self.root.clone(),
+            addr: format!("{hostname}:{port}"),
+            base_path: self.base_path.clone(),
+            live_reload: self.live_reload,
+            reloader: Reloader::default(),
+        }
+    }
+}
+
+/// What [`Server::reply`] decided to send
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Reply {
+    /// A file, with its status code
+    File(path::PathBuf, u16),
+    /// A redirect to this location
+    Redirect(String),
+    /// The built-in not found page
+    NotFound,
+}
+
+pub(crate) struct Server {
+    root: path::PathBuf,
+    addr: String,
+    base_path: String,
+    live_reload: bool,
+    reloader: Reloader,
+}
+
+impl Server {
+    /// The location being served
+    pub(crate) fn root(&self) -> &path::Path {
+        self.root.as_path()
+    }
+
+    /// The address the server is available at
+    pub(crate) fn addr(&self) -> &str {
+        self.addr.as_str()
+    }
+
+    /// The URL the site is available at, including the base path
+    pub(crate) fn url(&self) -> String {
+        format!("http://{}{}/", self.addr, self.base_path)
+    }
+
+    /// Notifies browsers served by this server
+    pub(crate) fn reloader(&self) -> Reloader {
+        self.reloader.clone()
+    }
+
+    /// Serve requests until the process exits
+    pub(crate) fn serve(&self) -> Result<()> {
+        let server = tiny_http::Server::http(self.addr())
+            .map_err(|e| anyhow::format_err!("Could not listen on {}: {e}", self.addr()))?;
+        // Event streams stay open, so each request gets its own thread
+        thread::scope(|scope| {
+            for request in server.incoming_requests() {
+                scope.spawn(move || {
+                    if let Err(e) = self.respond(request) {
+                        log::error!("{e}");
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    fn respond(&self, request: tiny_http::Request) -> Result<()> {
+        // Query strings are often used for cache-busting
+        let url = request.url();
+        let url = url
+            .split_once('?')
+            .map(|(path, _)| path)
+            .unwrap_or(url)
+            .to_owned();
+        match url.as_str() {
+            SCRIPT_PATH if self.live_reload => {
+                request.respond(
+                    tiny_http::Response::from_string(SCRIPT)
+                        .with_header(content_type("text/javascript")),
+                )?;
+                Ok(())
+            }
+            EVENTS_PATH if self.live_reload => self.stream_events(request),
+            _ => match self.reloader.failure() {
+                Some(failure) => {
+                    request.respond(
+                        tiny_http::Response::from_string(inject_script(&error_page(&failure)))
+                            .with_status_code(500)
+                            .with_header(content_type("text/html")),
+                    )?;
+                    Ok(())
+                }
+                None => self.respond_with_file(request, &url),
+            },
+        }
+    }
+
+    fn respond_with_file(&self, request: tiny_http::Request, url: &str) -> Result<()> {
+        match self.reply(url) {
+            Reply::File(path, status) => self.send_file(request, &path, status),
+            Reply::Redirect(location) => {
+                request.respond(
+                    tiny_http::Response::empty(302).with_header(
+                        tiny_http::Header::from_str(&format!("Location: {location}"))
+                            .expect("formatted correctly"),
+                    ),
+                )?;
+                Ok(())
+            }
+            Reply::NotFound => {
+                request.respond(
+                    tiny_http::Response::from_string(
+                        "<h1> <center> 404: Page not found </center> </h1>",
+                    )
+                    .with_status_code(404)
+                    .with_header(content_type("text/html")),
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    /// How to answer a request for `url` from the site's files
+    fn reply(&self, url: &str) -> Reply {
+        if let Some(path) = self.resolve(url) {
+            return Reply::File(path, 200);
+        }
+        if url == "/" && !self.base_path.is_empty() {
+            // Lead to the site when it's mounted elsewhere
+            return Reply::Redirect(format!("{}/", self.base_path));
+        }
+        // Like GitHub Pages and Netlify, prefer the site's own page
+        let not_found = self.root.join("404.html");
+        if not_found.is_file() {
+            return Reply::File(not_found, 404);
+        }
+        Reply::NotFound
+    }
+
+    fn send_file(&self, request: tiny_http::Request, path: &path::Path, status: u16) -> Result<()> {
+        let mime = mime_guess::MimeGuess::from_path(path).first_raw();
+        if self.live_reload && mime == Some("text/html") {
+            let html = std::fs::read_to_string(path)?;
+            request.respond(
+                tiny_http::Response::from_string(inject_script(&html))
+                    .with_status_code(status)
+                    .with_header(content_type("text/html")),
+            )?;
+        } else {
+            let mut response =
+                tiny_http::Response::from_file(std::fs::File::open(path)?).with_status_code(status);
+            if let Some(mime) = mime {
+                response.add_header(content_type(mime));
+            }
+            request.respond(response)?;
+        }
+        Ok(())
+    }
+
+    /// The file to serve for `url`, either the file itself or a directory's `index.html`
+    fn resolve(&self, url: &str) -> Option<path::PathBuf> {
+        let rel = url.strip_prefix(&self.base_path)?;
+        if !(rel.is_empty() || rel.starts_with('/')) {
+            return None;
+        }
+        let rel = rel.trim_start_matches('/');
+        if rel.split('/').any(|part| part == "..") {
+            return None;
+        }
+        let path = self.root.join(rel);
+        if path.is_file() {
+            return Some(path);
+        }
+        let index = path.join("index.html");
+        index.is_file().then_some(index)
+    }
+
+    /// Hold the connection open, sending a message per rebuild
+    fn stream_events(&self, request: tiny_http::Request) -> Result<()> {
+        let changes = self.reloader.subscribe();
+        let mut writer = request.into_writer();
+        writer.write_all(
+            b"HTTP/1.1 200 OK\r\n\
+            Content-Type: text/event-stream\r\n\
+            Cache-Control: no-cache\r\n\
+            Connection: close\r\n\r\n",
+        )?;
+        writer.flush()?;
+        loop {
+            let message = match changes.recv_timeout(KEEP_ALIVE) {
+                Ok(change) => format!("data: {}\n\n", change.as_str()),
+                Err(mpsc::RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_owned(),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            };
+            if writer
+                .write_all(message.as_bytes())
+                .and_then(|()| writer.flush())
+                .is_err()
+            {
+                // The page was closed or navigated away
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Load the live reload script at the end of `html`'s body
+fn inject_script(html: &str) -> String {
+    let script = format!("<script src=\"{SCRIPT_PATH}\"></script>");
+    // ASCII lowercasing keeps byte offsets the same
+    match html.to_ascii_lowercase().rfind("</body>") {
+        Some(end) => format!("{}{script}{}", &html[..end], &html[end..]),
+        None => format!("{html}{script}"),
+    }
+}
+
+/// Stands in for every page while the site fails to build
+fn error_page(failure: &str) -> String {
+    let failure = failure
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Build failed</title>
+<style>
+body {{ margin: 2em; font-family: sans-serif; }}
+h1 {{ color: #b00020; }}
+pre {{ padding: 1em; overflow-x: auto; background: #f6f6f6; border-left: 4px solid #b00020; }}
+</style>
+</head>
+<body>
+<h1>Build failed</h1>
+<p>This page reloads once the site builds again.</p>
+<pre>{failure}</pre>
+</body>
+</html>
+"#
+    )
+}
+
+fn content_type(mime: &str) -> tiny_http::Header {
+    tiny_http::Header::from_str(&format!("Content-Type: {mime}")).expect("formatted correctly")
+}
+
+fn available_port(host: &str) -> Option<u16> {
+    // Start after "well-known" ports (0–1023) as they require superuser
+    // privileges on UNIX-like operating systems.
+    (1024..9000).find(|port| std::net::TcpListener::bind((host, *port)).is_ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inject_before_body_end() {
+        assert_eq!(
+            inject_script("<html><BODY><p>Hi</p></BODY></html>"),
+            "<html><BODY><p>Hi</p><script src=\"/_cobalt/livereload.js\"></script></BODY></html>"
+        );
+        assert_eq!(
+            inject_script("<p>Fragment</p>"),
+            "<p>Fragment</p><script src=\"/_cobalt/livereload.js\"></script>"
+        );
+    }
+
+    #[test]
+    fn resolve_under_base_path() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("guide")).unwrap();
+        std::fs::write(root.path().join("index.html"), "").unwrap();
+        std::fs::write(root.path().join("guide/index.html"), "").unwrap();
+        std::fs::write(root.path().join("style.css"), "").unwrap();
+        let server = ServerBuilder::new(root.path())
+            .port(4000)
+            .base_path("docs/")
+            .build();
+
+        assert_eq!(server.url(), "http://localhost:4000/docs/");
+        assert_eq!(
+            server.resolve("/docs/"),
+            Some(root.path().join("index.html"))
+        );
+        assert_eq!(
+            server.resolve("/docs/guide"),
+            Some(root.path().join("guide/index.html"))
+        );
+        assert_eq!(
+            server.resolve("/docs/style.css"),
+            Some(root.path().join("style.css"))
+        );
+        assert_eq!(server.resolve("/style.css"), None);
+        assert_eq!(server.resolve("/docsstyle.css"), None);
+        assert_eq!(server.resolve("/docs/../secret"), None);
+    }
+
+    #[test]
+    fn redirect_to_base_path() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("index.html"), "").unwrap();
+        let server = ServerBuilder::new(root.path())
+            .port(4000)
+            .base_path("/docs")
+            .build();
+        assert_eq!(server.reply("/"), Reply::Redirect("/docs/".to_owned()));
+        assert_eq!(
+            server.reply("/docs/"),
+            Reply::File(root.path().join("index.html"), 200)
+        );
+
+        let server = ServerBuilder::new(root.path()).port(4000).build();
+        assert_eq!(
+            server.reply("/"),
+            Reply::File(root.path().join("index.html"), 200)
+        );
+        std::fs::remove_file(root.path().join("index.html")).unwrap();
+        assert_eq!(server.reply("/"), Reply::NotFound);
+    }
+
+    #[test]
+    fn serve_site_not_found_page() {
+        let root = tempfile::tempdir().unwrap();
+        let server = ServerBuilder::new(root.path())
+            .port(4000)
+            .base_path("docs")
+            .build();
+        assert_eq!(server.reply("/docs/missing"), Reply::NotFound);
+
+        std::fs::write(root.path().join("404.html"), "").unwrap();
+        assert_eq!(
+            server.reply("/docs/missing"),
+            Reply::File(root.path().join("404.html"), 404)
+        );
+        assert_eq!(
+            server.reply("/elsewhere"),
+            Reply::File(root.path().join("404.html"), 404)
+        );
+    }
+
+    #[test]
+    fn classify_changes() {
+        let paths = |paths: &'static [&'static str]| paths.iter().map(path::Path::new);
+        assert_eq!(
+            Change::from_paths(paths(&["_sass/_base.scss", "style.css"])),
+            Change::Css
+        );
+        assert_eq!(
+            Change::from_paths(paths(&["style.css", "index.md"])),
+            Change::Page
+        );
+        assert_eq!(Change::from_paths(paths(&[])), Change::Page);
+    }
+
+    #[test]
+    fn escape_failures() {
+        let page = error_page("Unknown index\n  with:\n    requested=<p>");
+        assert!(
+            page.contains("<pre>Unknown index\n  with:\n    requested=&lt;p&gt;</pre>"),
+            "{page}"
+        );
+    }
+
+    #[test]
+    fn clear_failure_on_rebuild() {
+        let reloader = Reloader::default();
+        let browser = reloader.subscribe();
+        reloader.fail("Build failed".to_owned());
+        assert_eq!(reloader.failure().as_deref(), Some("Build failed"));
+        // The error page has no stylesheets to swap
+        reloader.notify(Change::Css);
+        assert_eq!(reloader.failure(), None);
+        assert_eq!(browser.try_iter().collect::<Vec<_>>(), [Change::Page; 2]);
+    }
+
+    #[test]
+    fn notify_connected_browsers() {
+        let reloader = Reloader::default();
+        let browser = reloader.subscribe();
+        drop(reloader.subscribe());
+        reloader.notify(Change::Css);
+        assert_eq!(browser.try_recv(), Ok(Change::Css));
+        assert_eq!(reloader.clients.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,334 @@
+//! Rebuild the site as its sources change
+
+use std::path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use cobalt::cobalt_model;
+
+use crate::build;
+use crate::error::Result;
+
+/// How long events must stop arriving before rebuilding, so saving many files rebuilds once
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Backup, swap and probe files editors write next to the file being saved
+const EDITOR_FILES: &[&str] = &["*~", "*.swp", "*.swx", "#*#", "4913"];
+
+type Events = mpsc::Receiver<notify::Result<notify::Event>>;
+
+/// Rebuild whenever a source file changes, until the watcher stops
+///
+/// `config` is what the site was last built with. When `config_file` changes, the config is
+/// reloaded with `load_config`. `rebuilt` is told the outcome of each build and the files that
+/// triggered it.
+pub(crate) fn watch(
+    mut config: cobalt_config::Config,
+    config_file: &path::Path,
+    load_config: impl Fn() -> Result<cobalt_config::Config>,
+    layouts: &mut cobalt::LayoutCache,
+    mut rebuilt: impl FnMut(&Result<()>, &[path::PathBuf]),
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).with_context(|| anyhow::format_err!("Notify error"))?;
+    let mut filter = Filter::new(&config, config_file)?;
+    filter.watch(&mut watcher)?;
+    log::info!("Watching {} for changes", filter.source.display());
+
+    while let Some(changed) = next_changes(&rx, &filter)? {
+        if changed.contains(&filter.config_file) {
+            log::info!("Reloading config `{}`", config_file.display());
+            match load_config() {
+                Ok(reloaded) => {
+                    // The source, destination and ignores may have moved
+                    let reloaded_filter = Filter::new(&reloaded, config_file)?;
+                    filter.unwatch(&mut watcher);
+                    reloaded_filter.watch(&mut watcher)?;
+                    filter = reloaded_filter;
+                    config = reloaded;
+                    // Layouts were compiled with the old settings, e.g. the syntax theme
+                    *layouts = cobalt::LayoutCache::new();
+                }
+                Err(fail) => {
+                    let result = Err(fail);
+                    report(&result);
+                    rebuilt(&result, &changed);
+                    continue;
+                }
+            }
+        }
+
+        let result = cobalt_model::Config::from_config(config.clone())
+            .and_then(|config| build::build_with_layouts(config, layouts));
+        report(&result);
+        rebuilt(&result, &changed);
+    }
+
+    Ok(())
+}
+
+fn report(result: &Result<()>) {
+    if let Err(fail) = result {
+        log::error!("build failed\n{fail:?}");
+    }
+}
+
+/// Wait for a change worth rebuilding for, then gather the rest of its burst
+///
+/// Returns `None` once the watcher stops.
+fn next_changes(events: &Events, filter: &Filter) -> Result<Option<Vec<path::PathBuf>>> {
+    let mut changed: Vec<path::PathBuf> = Vec::new();
+    loop {
+        let event = if changed.is_empty() {
+            match events.recv() {
+                Ok(event) => event,
+                Err(mpsc::RecvError) => return Ok(None),
+            }
+        } else {
+            match events.recv_timeout(DEBOUNCE) {
+                Ok(event) => event,
+                Err(mpsc::RecvTimeoutError::Timeout | mpsc::RecvTimeoutError::Disconnected) => {
+                    return Ok(Some(changed));
+                }
+            }
+        };
+        let event = event.with_context(|| anyhow::format_err!("Notify error"))?;
+        match event.kind {
+            notify::EventKind::Create(_)
+            | notify::EventKind::Modify(_)
+            | notify::EventKind::Remove(_) => {
+                log::trace!("Noticed {:?} for {:#?}", event.kind, event.paths);
+            }
+            _ => {
+                continue;
+            }
+        }
+        for path in event.paths {
+            if !filter.includes(&path) {
+                log::trace!("Ignored change to `{}`", path.display());
+            } else if !changed.contains(&path) {
+                log::debug!("Changed `{}`", path.display());
+                changed.push(path);
+            }
+        }
+    }
+}
+
+/// Which changed paths are worth a rebuild
+#[derive(Debug)]
+struct Filter {
+    source: path::PathBuf,
+    destination: path::PathBuf,
+    config_file: path::PathBuf,
+    files: cobalt_core::Source,
+}
+
+impl Filter {
+    fn new(config: &cobalt_config::Config, config_file: &path::Path) -> Result<Self> {
+        // canonicalize is to ensure there is no question that `watcher`s paths come back safe for
+        // Source::includes_file
+        let source = config.source.to_path(&config.root);
+        let source = dunce::canonicalize(&source).with_context(|| {
+            anyhow::format_err!("Failed in processing source `{}`", source.display())
+        })?;
+
+        // Also canonicalize the destination folder. In particular for Windows, notify-rs
+        // generates the absolute path by prepending the above source path.
+        // On Windows canonicalize() adds a \\?\ to the start of the path.
+        let destination = config
+            .abs_dest
+            .clone()
+            .unwrap_or_else(|| config.destination.to_path(&config.root));
+        let destination = dunce::canonicalize(&destination).unwrap_or(destination);
+
+        let config_file = dunce::canonicalize(config_file).unwrap_or_else(|_| {
+            // Not created yet
+            let dir = config_file.parent().unwrap_or(path::Path::new("."));
+            let dir = dunce::canonicalize(dir).unwrap_or_else(|_| dir.to_owned());
+            dir.join(config_file.file_name().unwrap_or_default())
+        });
+
+        // Unlike when finding pages, `_layouts`, `_includes`, `_data`, `_sass` and other `_`
+        // directories stay in: the build reads them too
+        let ignore = [".*"]
+            .into_iter()
+            .chain(EDITOR_FILES.iter().copied())
+            .chain(config.ignore.iter().map(|s| s.as_str()));
+        let files = cobalt_core::Source::new(&source, ignore)?;
+
+        Ok(Self {
+            source,
+            destination,
+            config_file,
+            files,
+        })
+    }
+
+    fn includes(&self, path: &path::Path) -> bool {
+        if path == self.config_file {
+            true
+        } else if path.starts_with(&self.destination) || !path.starts_with(&self.source) {
+            false
+        } else {
+            self.files.includes_file(path)
+        }
+    }
+
+    fn watch(&self, watcher: &mut impl notify::Watcher) -> Result<()> {
+        watcher
+            .watch(&self.source, notify::RecursiveMode::Recursive)
+            .with_context(|| anyhow::format_err!("Notify error"))?;
+        if let Some(dir) = self.config_dir() {
+            // Editors often replace the file rather than write to it, so watch its directory
+            watcher
+                .watch(dir, notify::RecursiveMode::NonRecursive)
+                .with_context(|| anyhow::format_err!("Notify error"))?;
+        }
+        Ok(())
+    }
+
+    fn unwatch(&self, watcher: &mut impl notify::Watcher) {
+        // Already gone if the directory was removed
+        let _ = watcher.unwatch(&self.source);
+        if let Some(dir) = self.config_dir() {
+            let _ = watcher.unwatch(dir);
+        }
+    }
+
+    /// Where the config file lives, when that's outside of the source
+    fn config_dir(&self) -> Option<&path::Path> {
+        self.config_file
+            .parent()
+            .filter(|dir| !dir.starts_with(&self.source) && dir.is_dir())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn site() -> (tempfile::TempDir, cobalt_config::Config) {
+        let root = tempfile::tempdir().unwrap();
+        let root_path = dunce::canonicalize(root.path()).unwrap();
+        let config = cobalt_config::Config {
+            root: root_path,
+            ignore: vec!["/drafts-archive".into()],
+            ..Default::default()
+        };
+        (root, config)
+    }
+
+    fn event(kind: notify::EventKind, path: path::PathBuf) -> notify::Result<notify::Event> {
+        Ok(notify::Event::new(kind).add_path(path))
+    }
+
+    #[test]
+    fn skip_ignored_paths() {
+        let (_root, config) = site();
+        let root = config.root.clone();
+        let filter = Filter::new(&config, &root.join("_cobalt.yml")).unwrap();
+
+        assert!(filter.includes(&root.join("index.md")));
+        assert!(filter.includes(&root.join("_cobalt.yml")));
+        assert!(filter.includes(&root.join("_layouts/default.liquid")));
+        assert!(filter.includes(&root.join("_includes/header.liquid")));
+        assert!(filter.includes(&root.join("_data/authors.yml")));
+        assert!(filter.includes(&root.join("_sass/_base.scss")));
+
+        assert!(!filter.includes(&root.join("_site/index.html")));
+        assert!(!filter.includes(&root.join(".git/index")));
+        assert!(!filter.includes(&root.join("posts/.hello.md.swp")));
+        assert!(!filter.includes(&root.join("posts/hello.md~")));
+        assert!(!filter.includes(&root.join("drafts-archive/old.md")));
+    }
+
+    #[test]
+    fn debounce_bursts() {
+        let (_root, config) = site();
+        let root = config.root.clone();
+        let filter = Filter::new(&config, &root.join("_cobalt.yml")).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let modify = notify::EventKind::Modify(notify::event::ModifyKind::Any);
+        tx.send(event(modify, root.join(".git/index"))).unwrap();
+        tx.send(event(modify, root.join("index.md"))).unwrap();
+        tx.send(event(
+            notify::EventKind::Access(notify::event::AccessKind::Any),
+            root.join("about.md"),
+        ))
+        .unwrap();
+        tx.send(event(modify, root.join("style.scss"))).unwrap();
+        tx.send(event(modify, root.join("index.md"))).unwrap();
+
+        assert_eq!(
+            next_changes(&rx, &filter).unwrap(),
+            Some(vec![root.join("index.md"), root.join("style.scss")])
+        );
+        drop(tx);
+        assert_eq!(next_changes(&rx, &filter).unwrap(), None);
+    }
+
+    #[test]
+    fn reload_theme() {
+        let (_root, config) = site();
+        let root = config.root.clone();
+        let config_file = root.join("_cobalt.yml");
+        let set_theme = |theme: &str| {
+            std::fs::write(
+                &config_file,
+                format!("syntax_highlight:\n  theme: {theme}\n"),
+            )
+            .unwrap();
+        };
+        std::fs::create_dir_all(root.join("_layouts")).unwrap();
+        std::fs::write(
+            root.join("_layouts/default.liquid"),
+            "{% highlight rust %}fn main() {}{% endhighlight %}",
+        )
+        .unwrap();
+        std::fs::write(root.join("index.md"), "---\nlayout: default.liquid\n---\n").unwrap();
+        set_theme("base16-ocean.dark");
+
+        let load_config = {
+            let config_file = config_file.clone();
+            move || Ok(cobalt_config::Config::from_file(&config_file)?)
+        };
+        let config = load_config().unwrap();
+        let mut layouts = cobalt::LayoutCache::new();
+        build::build_with_layouts(
+            cobalt_model::Config::from_config(config.clone()).unwrap(),
+            &mut layouts,
+        )
+        .unwrap();
+        let index = root.join("_site/index.html");
+        let dark = std::fs::read_to_string(&index).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        {
+            let config_file = config_file.clone();
+            std::thread::spawn(move || {
+                watch(
+                    config,
+                    &config_file,
+                    load_config,
+                    &mut layouts,
+                    |result, _| {
+                        let _ = tx.send(result.is_ok());
+                    },
+                )
+            });
+        }
+        // The watcher may not be listening yet
+        let rebuilt = (0..10).find_map(|_| {
+            set_theme("InspiredGitHub");
+            rx.recv_timeout(Duration::from_secs(1)).ok()
+        });
+        assert_eq!(rebuilt, Some(true));
+
+        let light = std::fs::read_to_string(&index).unwrap();
+        assert_ne!(dark, light);
+        assert!(light.contains("background-color:#ffffff"), "{light}");
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs;
 use std::io::Write;
 use std::path;
@@ -7,7 +7,6 @@ use anyhow::Context as _;
 use jsonfeed::Feed;
 use log::debug;
 use log::trace;
-use log::warn;
 use sitemap::writer::SiteMapWriter;
 
 use crate::cobalt_model;
@@ -15,11 +14,17 @@ use crate::cobalt_model::Collection;
 use crate::cobalt_model::files;
 use crate::cobalt_model::permalink;
 use crate::cobalt_model::{Config, Minify, SortOrder};
+use crate::compress;
 use crate::document::{Document, RenderContext};
 use crate::error::Result;
+use crate::format::Formats;
+use crate::globals::Globals;
+use crate::integrity;
+use crate::layout::LayoutCache;
 use crate::pagination;
+use crate::syntax_highlight;
 
-struct Context {
+struct Context<'l> {
     pub(crate) destination: path::PathBuf,
     pub(crate) source_files: cobalt_core::Source,
     pub(crate) page_extensions: Vec<liquid::model::KString>,
@@ -28,15 +33,20 @@ struct Context {
     pub(crate) posts: Collection,
     pub(crate) site: cobalt_model::Site,
     pub(crate) site_attributes: liquid::Object,
-    pub(crate) layouts: HashMap<String, String>,
+    pub(crate) layouts: &'l LayoutCache,
     pub(crate) liquid: cobalt_model::Liquid,
-    pub(crate) markdown: cobalt_model::Markdown,
+    pub(crate) formats: Formats,
     pub(crate) assets: cobalt_model::Assets,
+    /// Where assets were written, which pages don't overwrite
+    pub(crate) asset_outputs: HashSet<path::PathBuf>,
+    pub(crate) syntax_stylesheet: Option<(cobalt_config::RelPath, String)>,
     pub(crate) minify: Minify,
+    pub(crate) csp: cobalt_config::Csp,
+    pub(crate) compress: cobalt_config::Compress,
 }
 
-impl Context {
-    fn with_config(config: Config) -> Result<Self> {
+impl<'l> Context<'l> {
+    fn with_config(config: Config, layouts: &'l mut LayoutCache) -> Result<Self> {
         let Config {
             source,
             destination,
@@ -49,19 +59,27 @@ impl Context {
             layouts_path,
             liquid,
             markdown,
-            syntax: _,
+            syntax,
+            syntax_stylesheet,
             assets,
             minify,
+            csp,
+            compress,
         } = config;
 
         let source_files = cobalt_core::Source::new(&source, ignore.iter().map(|s| s.as_str()))?;
         let site_attributes = site.load(&source)?;
         let liquid = liquid.build()?;
-        let markdown = markdown.build();
+        let formats = Formats::new(&markdown.build());
         let assets = assets.build()?;
+        let syntax_stylesheet = syntax_stylesheet
+            .map(|stylesheet| -> Result<_> {
+                let css = syntax_highlight::stylesheet(&syntax, &stylesheet.themes)?;
+                Ok((stylesheet.path, css))
+            })
+            .transpose()?;
 
-        let layouts = find_layouts(&layouts_path)?;
-        let layouts = parse_layouts(&layouts);
+        layouts.update(&layouts_path, &liquid)?;
 
         let context = Context {
             destination,
@@ -74,9 +92,13 @@ impl Context {
             site_attributes,
             layouts,
             liquid,
-            markdown,
+            formats,
             assets,
+            asset_outputs: HashSet::new(),
+            syntax_stylesheet,
             minify,
+            csp,
+            compress,
         };
         Ok(context)
     }
@@ -84,7 +106,12 @@ impl Context {
 
 /// The primary build function that transforms a directory into a site
 pub fn build(config: Config) -> Result<()> {
-    let context = Context::with_config(config)?;
+    build_with_layouts(config, &mut LayoutCache::new())
+}
+
+/// Build the site, reusing layouts compiled by previous builds
+pub fn build_with_layouts(config: Config, layouts: &mut LayoutCache) -> Result<()> {
+    let mut context = Context::with_config(config, layouts)?;
 
     let mut post_paths = Vec::new();
     let mut post_draft_paths = Vec::new();
@@ -108,15 +135,60 @@ pub fn build(config: Config) -> Result<()> {
         }
     }
 
-    let mut posts = parse_pages(&post_paths, &context.posts, context.include_drafts)?;
+    // copy all remaining files in the source to the destination
+    // compile SASS along the way
+    // and do it before rendering so documents can hash assets with `sri_hash`
+    let mut asset_outputs = HashSet::new();
+    for asset_path in asset_paths {
+        context
+            .assets
+            .process(&asset_path.abs_path, &context.destination, &context.minify)?;
+        asset_outputs.extend(
+            context
+                .assets
+                .output_path(&asset_path.abs_path, &context.destination),
+        );
+    }
+    context
+        .assets
+        .write_bundles(&context.destination, &context.minify)?;
+    asset_outputs.extend(context.assets.bundle_paths(&context.destination));
+    if let Some((path, css)) = &context.syntax_stylesheet {
+        let path = path.to_path(&context.destination);
+        debug!(
+            "Writing syntax highlighting stylesheet to `{}`",
+            path.display()
+        );
+        files::write_document_file(css, &path)?;
+        asset_outputs.insert(path);
+    }
+    context.asset_outputs = asset_outputs;
+    let mut hashes = integrity::InlineHashes::default();
+
+    let mut posts = parse_pages(
+        &post_paths,
+        &context.posts,
+        context.include_drafts,
+        &context.formats,
+    )?;
     if !post_draft_paths.is_empty() {
-        parse_drafts(&post_draft_paths, &mut posts, &context.posts)?;
+        parse_drafts(
+            &post_draft_paths,
+            &mut posts,
+            &context.posts,
+            &context.formats,
+        )?;
     }
 
-    let documents = parse_pages(&page_paths, &context.pages, context.include_drafts)?;
+    let documents = parse_pages(
+        &page_paths,
+        &context.pages,
+        context.include_drafts,
+        &context.formats,
+    )?;
 
     sort_pages(&mut posts, &context.posts)?;
-    generate_posts(&mut posts, &context)?;
+    generate_posts(&mut posts, &context, &mut hashes)?;
 
     // check if we should create an RSS file and create it!
     if let Some(ref path) = context.posts.rss {
@@ -143,95 +215,143 @@ pub fn build(config: Config) -> Result<()> {
         create_sitemap(&path, &posts, &documents, context.site.base_url.as_deref())?;
     }
 
-    generate_pages(posts, documents, &context)?;
+    let page_outputs: HashSet<path::PathBuf> = posts
+        .iter()
+        .chain(&documents)
+        .map(|doc| doc.file_path.to_path(&context.destination))
+        .collect();
+    generate_pages(posts, documents, &context, &mut hashes)?;
 
-    // copy all remaining files in the source to the destination
-    // compile SASS along the way
-    for asset_path in asset_paths {
-        context
-            .assets
-            .process(&asset_path.abs_path, &context.destination, &context.minify)?;
+    // after pages and assets, so a `robots.txt` of the site's own is noticed
+    if let Some(ref robots) = context.site.robots {
+        let path = context.destination.join("robots.txt");
+        if context.asset_outputs.contains(&path) || page_outputs.contains(&path) {
+            log::warn!("Skipping `site.robots`, the site has its own robots.txt");
+        } else {
+            create_robots(
+                &path,
+                robots,
+                context.site.sitemap.as_ref(),
+                context.site.base_url.as_deref(),
+            )?;
+        }
     }
 
+    if let Some(ref path) = context.csp.hashes_file {
+        let path = path.to_path(&context.destination);
+        debug!(
+            "Writing inline script and style hashes to `{}`",
+            path.display()
+        );
+        files::write_document_file(hashes.to_json(), path)?;
+    }
+
+    compress::compress_outputs(&context.destination, &context.compress)?;
+
     Ok(())
 }
 
 fn generate_collections_var(
-    posts_data: &[liquid::model::Value],
-    context: &Context,
-) -> (liquid::model::KString, liquid::model::Value) {
+    posts_data: Vec<liquid::model::Value>,
+    context: &Context<'_>,
+) -> liquid::Object {
     let mut posts_variable = context.posts.attributes();
-    posts_variable.insert(
-        "pages".into(),
-        liquid::model::Value::Array(posts_data.to_vec()),
-    );
-    let global_collection: liquid::Object = vec![(
+    posts_variable.insert("pages".into(), liquid::model::Value::Array(posts_data));
+    vec![(
         context.posts.slug.clone(),
         liquid::model::Value::Object(posts_variable),
     )]
     .into_iter()
-    .collect();
-    (
-        "collections".into(),
-        liquid::model::Value::Object(global_collection),
-    )
+    .collect()
 }
 
 fn generate_doc(
     doc: &mut Document,
-    context: &Context,
-    global_collection: (liquid::model::KString, liquid::model::Value),
+    context: &Context<'_>,
+    collections: Option<&liquid::Object>,
+    paginator: Option<&liquid::Object>,
+    hashes: &mut integrity::InlineHashes,
 ) -> Result<()> {
-    // Everything done with `globals` is terrible for performance.  liquid#95 allows us to
-    // improve this.
-    let mut globals: liquid::Object = vec![
-        (
-            "site".into(),
-            liquid::model::Value::Object(context.site_attributes.clone()),
-        ),
-        global_collection,
-    ]
-    .into_iter()
-    .collect();
-    globals.insert(
-        "page".into(),
-        liquid::model::Value::Object(doc.attributes.clone()),
-    );
-    {
+    let (excerpt, content) = {
         let render_context = RenderContext {
             parser: &context.liquid,
-            markdown: &context.markdown,
-            globals: &globals,
+            globals: Globals {
+                collections,
+                paginator,
+                ..Globals::new(&context.site_attributes, &doc.attributes)
+            },
             minify: context.minify.clone(),
         };
 
-        doc.render_excerpt(&render_context).with_context(|| {
+        let excerpt = doc.render_excerpt(&render_context).with_context(|| {
             anyhow::format_err!("Failed to render excerpt for {}", doc.file_path)
         })?;
-        doc.render_content(&render_context).with_context(|| {
+        let content = doc.render_content(&render_context).with_context(|| {
             anyhow::format_err!("Failed to render content for {}", doc.file_path)
         })?;
+        (excerpt, content)
+    };
+    doc.attributes.insert("excerpt".into(), excerpt);
+    doc.attributes
+        .insert("content".into(), liquid::model::Value::scalar(content.html));
+    doc.attributes.insert("toc".into(), content.toc);
+    for attachment in content.attachments {
+        let path = doc
+            .file_path
+            .with_file_name(&attachment.name)
+            .to_path(&context.destination);
+        files::write_document_file(attachment.data, path)?;
+    }
+
+    let dest_file = doc.file_path.to_path(&context.destination);
+    if context.asset_outputs.contains(&dest_file) {
+        log::warn!(
+            "Skipping {}, an asset is written to the same path",
+            doc.file_path
+        );
+        return Ok(());
     }
 
-    // Refresh `page` with the `excerpt` / `content` attribute
-    globals.insert(
-        "page".into(),
-        liquid::model::Value::Object(doc.attributes.clone()),
-    );
+    let hash_inline = context.csp.hashes || context.csp.hashes_file.is_some();
+    if hash_inline {
+        doc.attributes
+            .insert("csp".into(), integrity::InlineHashes::placeholders());
+    }
     let render_context = RenderContext {
         parser: &context.liquid,
-        markdown: &context.markdown,
-        globals: &globals,
+        globals: Globals {
+            collections,
+            paginator,
+            ..Globals::new(&context.site_attributes, &doc.attributes)
+        },
         minify: context.minify.clone(),
     };
-    let doc_html = doc
-        .render(&render_context, &context.layouts)
+    let mut doc_html = doc
+        .render(&render_context, context.layouts)
         .with_context(|| anyhow::format_err!("Failed to render for {}", doc.file_path))?;
-    files::write_document_file(doc_html, doc.file_path.to_path(&context.destination))?;
+    if hash_inline {
+        // Rendered once with stand-ins for `page.csp`, which are then swapped for the hashes
+        if integrity::InlineHashes::hashes_placeholders(&doc_html) {
+            log::warn!(
+                "`page.csp` is used inside an inline script or style in {}, so its hashes don't match",
+                doc.file_path
+            );
+        }
+        let page_hashes = integrity::InlineHashes::from_html(&doc_html);
+        doc_html = page_hashes.fill_placeholders(&doc_html);
+        doc.attributes.insert("csp".into(), page_hashes.to_value());
+        hashes.extend(&page_hashes);
+    }
+    files::write_document_file(doc_html, dest_file)?;
     Ok(())
 }
 
-fn generate_pages(posts: Vec<Document>, documents: Vec<Document>, context: &Context) -> Result<()> {
+fn generate_pages(
+    posts: Vec<Document>,
+    documents: Vec<Document>,
+    context: &Context<'_>,
+    hashes: &mut integrity::InlineHashes,
+) -> Result<()> {
     // during post rendering additional attributes such as content were
     // added to posts. collect them so that non-post documents can access them
     let posts_data: Vec<liquid::model::Value> = posts
@@ -239,6 +359,8 @@ fn generate_pages(posts: Vec<Document>, documents: Vec<Document>, context: &Cont
         .map(|x| liquid::model::Value::Object(x.attributes))
         .collect();
 
+    let collections = generate_collections_var(posts_data.clone(), context);
+
     trace!("Generating other documents");
     for mut doc in documents {
         trace!("Generating {}", doc.url_path);
@@ -249,43 +371,36 @@ fn generate_pages(posts: Vec<Document>, documents: Vec<Document>, context: &Cont
             let paginator = paginators
                 .next()
                 .expect("We detected pagination enabled but we have no paginator");
-            generate_doc(
-                &mut doc,
-                context,
-                (
-                    "paginator".into(),
-                    liquid::model::Value::Object(paginator.into()),
-                ),
-            )?;
+            generate_doc(&mut doc, context, None, Some(&paginator.into()), hashes)?;
             for paginator in paginators {
                 let mut doc_page = doc.clone();
                 doc_page.file_path = permalink::format_url_as_file(&paginator.index_permalink);
                 generate_doc(
                     &mut doc_page,
                     context,
-                    (
-                        "paginator".into(),
-                        liquid::model::Value::Object(paginator.into()),
-                    ),
+                    None,
+                    Some(&paginator.into()),
+                    hashes,
                 )?;
             }
         } else {
-            generate_doc(
-                &mut doc,
-                context,
-                generate_collections_var(&posts_data, context),
-            )?;
+            generate_doc(&mut doc, context, Some(&collections), None, hashes)?;
         };
     }
     Ok(())
 }
 
-fn generate_posts(posts: &mut [Document], context: &Context) -> Result<()> {
+fn generate_posts(
+    posts: &mut [Document],
+    context: &Context<'_>,
+    hashes: &mut integrity::InlineHashes,
+) -> Result<()> {
     // collect all posts attributes to pass them to other posts for rendering
     let simple_posts_data: Vec<liquid::model::Value> = posts
         .iter()
         .map(|x| liquid::model::Value::Object(x.attributes.clone()))
         .collect();
+    let collections = generate_collections_var(simple_posts_data.clone(), context);
 
     trace!("Generating posts");
     for (i, post) in &mut posts.iter_mut().enumerate() {
@@ -307,11 +422,7 @@ fn generate_posts(posts: &mut [Document], context: &Context) -> Result<()> {
         .unwrap_or(liquid::model::Value::Nil);
         post.attributes.insert("next".into(), next);
 
-        generate_doc(
-            post,
-            context,
-            generate_collections_var(&simple_posts_data, context),
-        )?;
+        generate_doc(post, context, Some(&collections), None, hashes)?;
     }
 
     Ok(())
@@ -342,6 +453,7 @@ fn parse_drafts(
     page_paths: &[cobalt_core::SourcePath],
     documents: &mut Vec<Document>,
     collection: &Collection,
+    formats: &Formats,
 ) -> Result<()> {
     let dir = &collection.dir;
     let drafts_dir = collection
@@ -362,7 +474,7 @@ fn parse_drafts(
         }
         .merge(&collection.default);
 
-        let doc = Document::parse(&file_path.abs_path, &new_path, default_front)
+        let doc = Document::parse(&file_path.abs_path, &new_path, default_front, formats)
             .with_context(|| anyhow::format_err!("Failed to parse {}", file_path.rel_path))?;
         documents.push(doc);
     }
@@ -373,13 +485,19 @@ fn parse_pages(
     page_paths: &[cobalt_core::SourcePath],
     collection: &Collection,
     include_drafts: bool,
+    formats: &Formats,
 ) -> Result<Vec<Document>> {
     let mut documents = vec![];
     for file_path in page_paths {
         let default_front = collection.default.clone();
 
-        let doc = Document::parse(&file_path.abs_path, &file_path.rel_path, default_front)
-            .with_context(|| anyhow::format_err!("Failed to parse {}", file_path.rel_path))?;
+        let doc = Document::parse(
+            &file_path.abs_path,
+            &file_path.rel_path,
+            default_front,
+            formats,
+        )
+        .with_context(|| anyhow::format_err!("Failed to parse {}", file_path.rel_path))?;
         if !doc.front.is_draft || include_drafts {
             documents.push(doc);
         } else {
@@ -389,45 +507,6 @@ fn parse_pages(
     Ok(documents)
 }
 
-fn find_layouts(layouts: &path::Path) -> Result<files::Files> {
-    let mut files = files::FilesBuilder::new(layouts)?;
-    files.ignore_hidden(false)?;
-    files.build()
-}
-
-fn parse_layouts(files: &files::Files) -> HashMap<String, String> {
-    let (entries, errors): (Vec<_>, Vec<_>) = files
-        .files()
-        .map(|file_path| {
-            let rel_src = file_path
-                .strip_prefix(files.root())
-                .expect("file was found under the root");
-
-            let layout_data = files::read_file(&file_path).with_context(|| {
-                anyhow::format_err!("Failed to load layout {}", rel_src.display())
-            })?;
-
-            let path = rel_src
-                .to_str()
-                .ok_or_else(|| {
-                    anyhow::format_err!("File name not valid liquid path: {}", rel_src.display())
-                })?
-                .to_owned();
-
-            Ok((path, layout_data))
-        })
-        .partition(Result::is_ok);
-
-    for error in errors {
-        warn!("{}", error.expect_err("partition to filter out oks"));
-    }
-
-    entries
-        .into_iter()
-        .map(|entry| entry.expect("partition to filter out errors"))
-        .collect()
-}
-
 // creates a new RSS file with the contents of the site blog
 fn create_rss(
     path: &path::Path,
@@ -530,6 +609,49 @@ fn create_sitemap(
     Ok(())
 }
 
+fn create_robots(
+    path: &path::Path,
+    robots: &cobalt_config::Robots,
+    sitemap: Option<&cobalt_config::RelPath>,
+    base_url: Option<&str>,
+) -> Result<()> {
+    debug!("Creating robots.txt file at {}", path.display());
+    let mut robots_string = String::new();
+    let default_rules = [cobalt_config::RobotsRule::default()];
+    let rules = if robots.rules.is_empty() {
+        &default_rules[..]
+    } else {
+        &robots.rules[..]
+    };
+    for (i, rule) in rules.iter().enumerate() {
+        if i != 0 {
+            robots_string.push('\n');
+        }
+        robots_string.push_str(&format!("User-agent: {}\n", rule.user_agent));
+        for allow in &rule.allow {
+            robots_string.push_str(&format!("Allow: {allow}\n"));
+        }
+        if rule.disallow.is_empty() && rule.allow.is_empty() {
+            robots_string.push_str("Disallow:\n");
+        }
+        for disallow in &rule.disallow {
+            robots_string.push_str(&format!("Disallow: {disallow}\n"));
+        }
+    }
+
+    if let Some(sitemap) = sitemap {
+        let link = base_url.as_ref().ok_or_else(|| {
+            anyhow::format_err!("`base_url` is required for robots.txt sitemap support")
+        })?;
+        robots_string.push_str(&format!("\nSitemap: {link}/{sitemap}\n"));
+    }
+    trace!("robots.txt data: {robots_string}");
+
+    files::write_document_file(robots_string, path)?;
+
+    Ok(())
+}
+
 pub fn classify_path<'s>(
     path: &relative_path::RelativePathBuf,
     pages: &'s Collection,
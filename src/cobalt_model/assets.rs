@@ -5,6 +5,8 @@ use anyhow::Context as _;
 use log::debug;
 use serde::{Deserialize, Serialize};
 
+use super::bundle;
+use super::css;
 use super::sass;
 use super::{Minify, files};
 
@@ -14,23 +16,54 @@ use crate::error::Result;
 #[serde(deny_unknown_fields, default)]
 pub struct AssetsBuilder {
     pub sass: sass::SassBuilder,
+    pub css: css::CssBuilder,
+    pub bundles: std::collections::BTreeMap<liquid::model::KString, cobalt_config::Bundle>,
     pub source: path::PathBuf,
+    pub destination: path::PathBuf,
 }
 
 impl AssetsBuilder {
-    pub fn from_config(config: cobalt_config::Assets, source: &path::Path) -> Self {
+    pub fn from_config(
+        config: cobalt_config::Assets,
+        source: &path::Path,
+        destination: &path::Path,
+    ) -> Self {
         Self {
             sass: sass::SassBuilder::from_config(config.sass, source),
+            css: css::CssBuilder::from_config(config.css),
+            bundles: config.bundles,
             source: source.to_owned(),
+            destination: destination.to_owned(),
         }
     }
 
     pub fn build(self) -> Result<Assets> {
-        let AssetsBuilder { sass, source } = self;
+        let AssetsBuilder {
+            sass,
+            css,
+            bundles,
+            source,
+            destination,
+        } = self;
 
         let sass = sass.build();
-
-        let assets = Assets { sass, source };
+        let css = css.build()?;
+        let candidates = if bundles.is_empty() {
+            Vec::new()
+        } else {
+            bundle::candidates(&source, &destination)
+        };
+        let bundles = bundles
+            .into_iter()
+            .map(|(name, config)| bundle::Bundle::resolve(name, config, &source, &candidates))
+            .collect::<Result<_>>()?;
+
+        let assets = Assets {
+            sass,
+            css,
+            bundles,
+            source,
+        };
         Ok(assets)
     }
 }
@@ -38,6 +71,8 @@ impl AssetsBuilder {
 #[derive(Debug)]
 pub struct Assets {
     sass: sass::SassCompiler,
+    css: css::CssTransform,
+    bundles: Vec<bundle::Bundle>,
     source: path::PathBuf,
 }
 
@@ -48,15 +83,17 @@ impl Assets {
         dest_root: &path::Path,
         minify: &Minify,
     ) -> Result<()> {
-        let rel_src = path
-            .strip_prefix(&self.source)
-            .expect("file was found under the root");
-        let dest_path = dest_root.join(rel_src);
+        let Some(dest_path) = self.output_path(path, dest_root) else {
+            debug!("Skipping `{}`, it is bundled", path.display());
+            return Ok(());
+        };
         if sass::is_sass_file(path) {
             self.sass
-                .compile_file(&self.source, dest_root, path, minify)?;
+                .compile_file(&self.source, dest_root, path, &self.css, minify)?;
         } else if path.extension() == Some(OsStr::new("js")) {
             copy_and_minify_js(path, &dest_path, minify.js)?;
+        } else if path.extension() == Some(OsStr::new("css")) && self.css.is_enabled() {
+            transform_css(path, &dest_path, &self.css, minify.css)?;
         } else if path.extension() == Some(OsStr::new("css")) {
             copy_and_minify_css(path, &dest_path, minify.css)?;
         } else {
@@ -64,12 +101,58 @@ impl Assets {
         }
         Ok(())
     }
+
+    /// Where [`Assets::process`] writes `path`, `None` when it's only bundled
+    pub fn output_path(&self, path: &path::Path, dest_root: &path::Path) -> Option<path::PathBuf> {
+        if self.bundles.iter().any(|bundle| bundle.contains(path)) {
+            return None;
+        }
+        let rel_src = path
+            .strip_prefix(&self.source)
+            .expect("file was found under the root");
+        let mut dest_path = dest_root.join(rel_src);
+        if cfg!(feature = "sass") && sass::is_sass_file(path) {
+            dest_path.set_extension("css");
+        }
+        Some(dest_path)
+    }
+
+    /// Where [`Assets::write_bundles`] writes each bundle
+    pub fn bundle_paths(&self, dest_root: &path::Path) -> Vec<path::PathBuf> {
+        self.bundles
+            .iter()
+            .map(|bundle| bundle.output_path(dest_root))
+            .collect()
+    }
+
+    /// Write each of `assets.bundles`
+    pub fn write_bundles(&self, dest_root: &path::Path, minify: &Minify) -> Result<()> {
+        for bundle in &self.bundles {
+            bundle.write(dest_root, &self.source, &self.css, minify)?;
+        }
+        Ok(())
+    }
+}
+
+fn transform_css(
+    src_file: &path::Path,
+    dest_file: &path::Path,
+    css: &css::CssTransform,
+    minify: bool,
+) -> Result<()> {
+    debug!(
+        "Transforming `{}` to `{}`",
+        src_file.display(),
+        dest_file.display()
+    );
+    let content = std::fs::read_to_string(src_file)?;
+    let transformed = css.transform(src_file, &content, minify)?;
+    files::write_document_file(transformed, dest_file)
 }
 
 #[cfg(feature = "html-minifier")]
 fn copy_and_minify_css(src_file: &path::Path, dest_file: &path::Path, minify: bool) -> Result<()> {
     if minify {
-        use html_minifier::css::minify;
         // create target directories if any exist
         if let Some(parent) = dest_file.parent() {
             std::fs::create_dir_all(parent)
@@ -82,15 +165,7 @@ fn copy_and_minify_css(src_file: &path::Path, dest_file: &path::Path, minify: bo
             dest_file.display()
         );
         let content = std::fs::read_to_string(src_file)?;
-        let minified = minify(&content)
-            .map_err(|e| {
-                anyhow::format_err!(
-                    "Could not minify css file {} error {}",
-                    src_file.to_string_lossy(),
-                    e
-                )
-            })?
-            .to_string();
+        let minified = minify_css(&content, src_file)?;
         std::fs::write(dest_file, minified)?;
     } else {
         files::copy_file(src_file, dest_file)?;
@@ -101,7 +176,6 @@ fn copy_and_minify_css(src_file: &path::Path, dest_file: &path::Path, minify: bo
 #[cfg(feature = "html-minifier")]
 fn copy_and_minify_js(src_file: &path::Path, dest_file: &path::Path, minify: bool) -> Result<()> {
     if minify {
-        use html_minifier::js::minify;
         // create target directories if any exist
         if let Some(parent) = dest_file.parent() {
             std::fs::create_dir_all(parent)
@@ -114,7 +188,7 @@ fn copy_and_minify_js(src_file: &path::Path, dest_file: &path::Path, minify: boo
             dest_file.display()
         );
         let content = std::fs::read_to_string(src_file)?;
-        let minified = minify(&content).to_string();
+        let minified = minify_js(&content);
         std::fs::write(dest_file, minified)?;
     } else {
         files::copy_file(src_file, dest_file)?;
@@ -122,6 +196,25 @@ fn copy_and_minify_js(src_file: &path::Path, dest_file: &path::Path, minify: boo
     Ok(())
 }
 
+#[cfg(feature = "html-minifier")]
+pub(super) fn minify_css(content: &str, src_file: &path::Path) -> Result<String> {
+    let minified = html_minifier::css::minify(content)
+        .map_err(|e| {
+            anyhow::format_err!(
+                "Could not minify css file {} error {}",
+                src_file.to_string_lossy(),
+                e
+            )
+        })?
+        .to_string();
+    Ok(minified)
+}
+
+#[cfg(feature = "html-minifier")]
+pub(super) fn minify_js(content: &str) -> String {
+    html_minifier::js::minify(content).to_string()
+}
+
 #[cfg(not(feature = "html-minifier"))]
 fn copy_and_minify_css(src_file: &path::Path, dest_file: &path::Path, _minify: bool) -> Result<()> {
     files::copy_file(src_file, dest_file)?;
@@ -133,3 +226,13 @@ fn copy_and_minify_js(src_file: &path::Path, dest_file: &path::Path, _minify: bo
     files::copy_file(src_file, dest_file)?;
     Ok(())
 }
+
+#[cfg(not(feature = "html-minifier"))]
+pub(super) fn minify_css(content: &str, _src_file: &path::Path) -> Result<String> {
+    Ok(content.to_owned())
+}
+
+#[cfg(not(feature = "html-minifier"))]
+pub(super) fn minify_js(content: &str) -> String {
+    content.to_owned()
+}
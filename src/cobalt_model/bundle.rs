@@ -0,0 +1,166 @@
+use std::path;
+
+use anyhow::Context as _;
+use ignore::overrides::OverrideBuilder;
+use liquid::model::KString;
+use log::debug;
+use walkdir::WalkDir;
+
+use super::css::CssTransform;
+use super::{Minify, assets, files};
+use crate::error::Result;
+
+/// A file concatenated from others, see `assets.bundles`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Bundle {
+    name: KString,
+    output: cobalt_config::RelPath,
+    inputs: Vec<path::PathBuf>,
+}
+
+impl Bundle {
+    /// Find the files matching each input glob, in the order the globs are listed
+    ///
+    /// `candidates` are every file under `source` in walk order.
+    pub(crate) fn resolve(
+        name: KString,
+        config: cobalt_config::Bundle,
+        source: &path::Path,
+        candidates: &[path::PathBuf],
+    ) -> Result<Self> {
+        let mut inputs: Vec<path::PathBuf> = Vec::new();
+        for glob in &config.inputs {
+            let mut matcher = OverrideBuilder::new(source);
+            matcher
+                .add(glob)
+                .with_context(|| format!("Invalid input `{glob}` for bundle `{name}`"))?;
+            let matcher = matcher.build()?;
+            let mut matched = false;
+            for candidate in candidates {
+                if matcher.matched(candidate, false).is_whitelist() {
+                    matched = true;
+                    if !inputs.contains(candidate) {
+                        inputs.push(candidate.clone());
+                    }
+                }
+            }
+            if !matched {
+                anyhow::bail!("Input `{glob}` for bundle `{name}` matched no files");
+            }
+        }
+        Ok(Self {
+            name,
+            output: config.output,
+            inputs,
+        })
+    }
+
+    pub(crate) fn contains(&self, path: &path::Path) -> bool {
+        self.inputs.iter().any(|input| input == path)
+    }
+
+    pub(crate) fn output_path(&self, dest_root: &path::Path) -> path::PathBuf {
+        self.output.to_path(dest_root)
+    }
+
+    /// Concatenate the inputs into `dest_root`, minifying or transforming by the output's
+    /// extension
+    pub(crate) fn write(
+        &self,
+        dest_root: &path::Path,
+        source: &path::Path,
+        css: &CssTransform,
+        minify: &Minify,
+    ) -> Result<()> {
+        let dest_file = self.output_path(dest_root);
+        debug!(
+            "Bundling {} files into `{}`",
+            self.inputs.len(),
+            dest_file.display()
+        );
+        let mut content = String::new();
+        for input in &self.inputs {
+            let input_content = std::fs::read_to_string(input)
+                .with_context(|| format!("Could not read {}", input.display()))?;
+            content.push_str(&input_content);
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+        }
+
+        let content = match self.output.extension() {
+            Some("css") if css.is_enabled() => {
+                css.transform(&self.output.to_path(source), &content, minify.css)?
+            }
+            Some("css") if minify.css => assets::minify_css(&content, &dest_file)?,
+            Some("js") if minify.js => assets::minify_js(&content),
+            _ => content,
+        };
+        files::write_document_file(content, dest_file)
+            .with_context(|| format!("Could not write bundle `{}`", self.name))
+    }
+}
+
+/// Every file a bundle could include: everything under `source` except hidden files and the
+/// destination
+pub(crate) fn candidates(source: &path::Path, destination: &path::Path) -> Vec<path::PathBuf> {
+    WalkDir::new(source)
+        .min_depth(1)
+        .follow_links(false)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|e| {
+            !e.file_name().to_string_lossy().starts_with('.') && e.path() != destination
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bundle(inputs: &[&str], root: &path::Path) -> Result<Bundle> {
+        let config = cobalt_config::Bundle {
+            output: "js/site.js".try_into().unwrap(),
+            inputs: inputs.iter().map(|glob| KString::from_ref(glob)).collect(),
+        };
+        let candidates = candidates(root, &root.join("_site"));
+        Bundle::resolve("site".into(), config, root, &candidates)
+    }
+
+    #[test]
+    fn resolve_inputs_in_order() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        for file in [
+            "_js/vendor/b.js",
+            "_js/vendor/a.js",
+            "_js/app.js",
+            "_site/old.js",
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+
+        let resolved = bundle(&["_js/app.js", "_js/vendor/*.js", "*.js"], root).unwrap();
+        assert_eq!(
+            resolved.inputs,
+            vec![
+                root.join("_js/app.js"),
+                root.join("_js/vendor/a.js"),
+                root.join("_js/vendor/b.js"),
+            ]
+        );
+        assert!(resolved.contains(&root.join("_js/vendor/a.js")));
+
+        let missing = bundle(&["_js/missing/*.js"], root).unwrap_err();
+        assert_eq!(
+            missing.to_string(),
+            "Input `_js/missing/*.js` for bundle `site` matched no files"
+        );
+    }
+}
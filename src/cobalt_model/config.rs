@@ -13,6 +13,7 @@ use super::mark;
 use super::site;
 use super::template;
 use crate::SyntaxHighlight;
+use crate::syntax_highlight::classes_theme;
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(deny_unknown_fields, default)]
@@ -30,8 +31,11 @@ pub struct Config {
     pub markdown: mark::MarkdownBuilder,
     #[serde(skip)]
     pub syntax: std::sync::Arc<SyntaxHighlight>,
+    pub syntax_stylesheet: Option<cobalt_config::HighlightStylesheet>,
     pub assets: assets::AssetsBuilder,
     pub minify: cobalt_config::Minify,
+    pub csp: cobalt_config::Csp,
+    pub compress: cobalt_config::Compress,
 }
 
 impl Config {
@@ -49,10 +53,14 @@ impl Config {
             template_extensions,
             ignore: custom_ignore,
             syntax_highlight,
+            markdown,
             layouts_dir,
             includes_dir,
+            shortcodes_dir,
             assets,
             minify,
+            csp,
+            compress,
         } = source;
 
         if include_drafts {
@@ -62,6 +70,7 @@ impl Config {
         if template_extensions.is_empty() {
             anyhow::bail!("`template_extensions` should not be empty.");
         }
+        crate::math::ensure_supported(&markdown)?;
 
         let source = source.to_path(&root);
         let destination = abs_dest.unwrap_or_else(|| destination.to_path(root));
@@ -82,8 +91,14 @@ impl Config {
         }
         ignore.push(format!("/{includes_dir}").into());
         ignore.push(format!("/{layouts_dir}").into());
+        ignore.push(format!("/{shortcodes_dir}").into());
         ignore.push("/_defaults".into());
         ignore.push(format!("/{}", assets.sass.import_dir).into());
+        for load_path in &assets.sass.load_paths {
+            ignore.push(format!("/{load_path}").into());
+        }
+        ignore.push(format!("/{}", syntax_highlight.syntaxes_dir).into());
+        ignore.push(format!("/{}", syntax_highlight.themes_dir).into());
         assert_eq!(pages.dir, "");
         assert_eq!(pages.drafts_dir, None);
         ignore.push(format!("!/{}", posts.dir).into());
@@ -92,31 +107,47 @@ impl Config {
         }
         ignore.extend(custom_ignore);
 
-        let assets = assets::AssetsBuilder::from_config(assets, &source);
+        let assets = assets::AssetsBuilder::from_config(assets, &source, &destination);
 
         let includes_path = source.join(includes_dir);
+        let shortcodes_path = source.join(shortcodes_dir);
         let layouts_path = source.join(layouts_dir);
 
-        let mut highlight = SyntaxHighlight::new();
-        let syntaxes_path = source.join("_syntaxes");
-        if syntaxes_path.exists() {
-            highlight.load_custom_syntaxes(&syntaxes_path);
-        }
-
+        let highlight = crate::syntax_highlight::load(
+            &source.join(syntax_highlight.syntaxes_dir.as_path()),
+            &source.join(syntax_highlight.themes_dir.as_path()),
+        )?;
         let syntax = std::sync::Arc::new(highlight);
 
-        let liquid = template::LiquidBuilder {
-            includes_path,
+        let theme = syntax_highlight
+            .enabled
+            .then(|| match syntax_highlight.mode {
+                cobalt_config::HighlightMode::Inline => syntax_highlight.theme.clone(),
+                cobalt_config::HighlightMode::Classes => classes_theme().into(),
+            });
+        let markdown = mark::MarkdownBuilder {
+            extensions: markdown,
             syntax: syntax.clone(),
-            theme: syntax_highlight
-                .enabled
-                .then(|| syntax_highlight.theme.clone()),
+            theme: theme.clone(),
         };
-        let markdown = mark::MarkdownBuilder {
+        let liquid = template::LiquidBuilder {
+            includes_path,
+            shortcodes_path,
+            source_path: source.clone(),
+            destination_path: destination.clone(),
+            bundles: assets
+                .bundles
+                .iter()
+                .map(|(name, bundle)| {
+                    (
+                        name.clone(),
+                        liquid::model::KString::from_ref(bundle.output.as_str()),
+                    )
+                })
+                .collect(),
             syntax: syntax.clone(),
-            theme: syntax_highlight
-                .enabled
-                .then(|| syntax_highlight.theme.clone()),
+            theme,
+            markdown: markdown.clone(),
         };
 
         let config = Config {
@@ -132,8 +163,11 @@ impl Config {
             liquid,
             markdown,
             syntax,
+            syntax_stylesheet: syntax_highlight.stylesheet,
             assets,
             minify,
+            csp,
+            compress,
         };
 
         Ok(config)
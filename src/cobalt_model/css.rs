@@ -0,0 +1,231 @@
+//! Lowering, prefixing and bundling for `.css` files and compiled Sass
+
+use std::path;
+
+use liquid::model::KString;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct CssBuilder {
+    pub targets: Option<KString>,
+    pub bundle: bool,
+}
+
+impl CssBuilder {
+    pub fn from_config(config: cobalt_config::Css) -> Self {
+        Self {
+            targets: config.targets,
+            bundle: config.bundle,
+        }
+    }
+
+    #[cfg(feature = "css-transform")]
+    pub fn build(self) -> Result<CssTransform> {
+        let Self { targets, bundle } = self;
+        let targets = targets.as_deref().map(parse_targets).transpose()?;
+        Ok(CssTransform { targets, bundle })
+    }
+
+    #[cfg(not(feature = "css-transform"))]
+    pub fn build(self) -> Result<CssTransform> {
+        if self.targets.is_some() || self.bundle {
+            log::warn!(
+                "CSS transforms require the `css-transform` feature; copying stylesheets unchanged"
+            );
+        }
+        Ok(CssTransform {})
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CssTransform {
+    #[cfg(feature = "css-transform")]
+    targets: Option<lightningcss::targets::Browsers>,
+    #[cfg(feature = "css-transform")]
+    bundle: bool,
+}
+
+impl CssTransform {
+    /// Whether stylesheets go through [`CssTransform::transform`] rather than being copied
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(feature = "css-transform")]
+        {
+            self.targets.is_some() || self.bundle
+        }
+        #[cfg(not(feature = "css-transform"))]
+        {
+            false
+        }
+    }
+
+    /// Transform `content`, read from `file_path`, for the configured targets
+    ///
+    /// Relative `@import`s are resolved from `file_path` when bundling.
+    #[cfg(feature = "css-transform")]
+    pub fn transform(&self, file_path: &path::Path, content: &str, minify: bool) -> Result<String> {
+        use lightningcss::bundler::Bundler;
+        use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+
+        let failed = |e: &dyn std::fmt::Display| {
+            anyhow::format_err!("Failed to transform `{}`: {e}", file_path.display())
+        };
+        let sources = Sources {
+            entry: file_path,
+            content,
+            files: lightningcss::bundler::FileProvider::new(),
+        };
+        let options = ParserOptions {
+            filename: file_path.display().to_string(),
+            ..Default::default()
+        };
+        let mut stylesheet = if self.bundle {
+            Bundler::new(&sources, None, options)
+                .bundle(file_path)
+                .map_err(|e| failed(&e))?
+        } else {
+            StyleSheet::parse(content, options).map_err(|e| failed(&e))?
+        };
+
+        stylesheet
+            .minify(MinifyOptions {
+                targets: self.targets.into(),
+                ..Default::default()
+            })
+            .map_err(|e| failed(&e))?;
+        let output = stylesheet
+            .to_css(PrinterOptions {
+                minify,
+                targets: self.targets.into(),
+                ..Default::default()
+            })
+            .map_err(|e| failed(&e))?;
+        Ok(output.code)
+    }
+
+    #[cfg(not(feature = "css-transform"))]
+    pub fn transform(
+        &self,
+        _file_path: &path::Path,
+        content: &str,
+        _minify: bool,
+    ) -> Result<String> {
+        Ok(content.to_owned())
+    }
+}
+
+/// Serves the stylesheet being transformed from memory and its imports from disk
+#[cfg(feature = "css-transform")]
+struct Sources<'c> {
+    entry: &'c path::Path,
+    content: &'c str,
+    files: lightningcss::bundler::FileProvider,
+}
+
+#[cfg(feature = "css-transform")]
+impl lightningcss::bundler::SourceProvider for Sources<'_> {
+    type Error = std::io::Error;
+
+    fn read<'a>(&'a self, file: &path::Path) -> std::io::Result<&'a str> {
+        if file == self.entry {
+            Ok(self.content)
+        } else {
+            self.files.read(file)
+        }
+    }
+
+    fn resolve(
+        &self,
+        specifier: &str,
+        originating_file: &path::Path,
+    ) -> std::io::Result<lightningcss::bundler::ResolveResult> {
+        if specifier.contains("://") || specifier.starts_with("//") {
+            // Left for the browser to load
+            Ok(lightningcss::bundler::ResolveResult::External(
+                specifier.to_owned(),
+            ))
+        } else {
+            self.files.resolve(specifier, originating_file)
+        }
+    }
+}
+
+/// Parse a browserslist query like `chrome >= 100, safari >= 15.4` or `> 0.5%, not dead`
+#[cfg(feature = "css-transform")]
+fn parse_targets(targets: &str) -> Result<lightningcss::targets::Browsers> {
+    lightningcss::targets::Browsers::from_browserslist([targets])
+        .map_err(|e| anyhow::format_err!("Invalid CSS targets `{targets}`: {e}"))?
+        .ok_or_else(|| anyhow::format_err!("CSS targets `{targets}` match no browsers"))
+}
+
+#[cfg(test)]
+#[cfg(feature = "css-transform")]
+mod test {
+    use super::*;
+
+    fn transform(targets: &str, content: &str) -> String {
+        CssBuilder {
+            targets: Some(KString::from_ref(targets)),
+            bundle: false,
+        }
+        .build()
+        .unwrap()
+        .transform(path::Path::new("style.css"), content, false)
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_browser_targets() {
+        let browsers = parse_targets("chrome >= 100, Safari 15.4, chrome 90").unwrap();
+        assert_eq!(browsers.chrome, Some(90 << 16));
+        assert_eq!(browsers.safari, Some((15 << 16) | (4 << 8)));
+        assert_eq!(browsers.firefox, None);
+
+        assert!(parse_targets("defaults").unwrap().firefox.is_some());
+
+        assert!(parse_targets("chrome").is_err());
+        assert!(parse_targets("netscape 4").is_err());
+        assert!(parse_targets("chrome 1.2.3.4").is_err());
+    }
+
+    #[test]
+    fn lower_nesting() {
+        assert_eq!(
+            transform(
+                "chrome 100",
+                ".nav {\n  color: red;\n  & a { color: blue; }\n}\n"
+            ),
+            ".nav {\n  color: red;\n}\n\n.nav a {\n  color: #00f;\n}\n"
+        );
+    }
+
+    #[test]
+    fn add_vendor_prefixes() {
+        let css = transform("safari 14", ".card { user-select: none; }");
+        assert!(css.contains("-webkit-user-select: none;"), "{css}");
+        let css = transform("chrome 120", ".card { user-select: none; }");
+        assert!(!css.contains("-webkit-"), "{css}");
+    }
+
+    #[test]
+    fn bundle_imports() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("_base.css"), "body { margin: 0 }\n").unwrap();
+        let entry = root.path().join("style.css");
+        let content = "@import \"https://example.com/font.css\";\n@import \"_base.css\";\n.a { color: red }\n";
+        let css = CssBuilder {
+            targets: None,
+            bundle: true,
+        }
+        .build()
+        .unwrap()
+        .transform(&entry, content, true)
+        .unwrap();
+        assert_eq!(
+            css,
+            "@import \"https://example.com/font.css\";body{margin:0}.a{color:red}"
+        );
+    }
+}
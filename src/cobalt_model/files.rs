@@ -289,14 +289,14 @@ pub fn copy_file(src_file: &path::Path, dest_file: &path::Path) -> Result<()> {
     Ok(())
 }
 
-pub fn write_document_file<S: AsRef<str>, P: AsRef<path::Path>>(
+pub fn write_document_file<S: AsRef<[u8]>, P: AsRef<path::Path>>(
     content: S,
     dest_file: P,
 ) -> Result<()> {
     write_document_file_internal(content.as_ref(), dest_file.as_ref())
 }
 
-fn write_document_file_internal(content: &str, dest_file: &path::Path) -> Result<()> {
+fn write_document_file_internal(content: &[u8], dest_file: &path::Path) -> Result<()> {
     // create target directories if any exist
     if let Some(parent) = dest_file.parent() {
         fs::create_dir_all(parent)
@@ -306,7 +306,7 @@ fn write_document_file_internal(content: &str, dest_file: &path::Path) -> Result
     let mut file = fs::File::create(dest_file)
         .with_context(|| anyhow::format_err!("Could not create {}", dest_file.display()))?;
 
-    file.write_all(content.as_bytes())?;
+    file.write_all(content)?;
     trace!("Wrote {}", dest_file.display());
     Ok(())
 }
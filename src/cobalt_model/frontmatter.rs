@@ -20,7 +20,8 @@ pub struct Frontmatter {
     pub tags: Vec<liquid::model::KString>,
     pub excerpt_separator: liquid::model::KString,
     pub published_date: Option<DateTime>,
-    pub format: SourceFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<SourceFormat>,
     pub templated: bool,
     pub layout: Option<liquid::model::KString>,
     pub is_draft: bool,
@@ -28,6 +29,8 @@ pub struct Frontmatter {
     pub collection: liquid::model::KString,
     pub data: liquid::Object,
     pub pagination: Option<pagination::PaginationConfig>,
+    #[serde(skip_serializing_if = "cobalt_config::Markdown::is_empty")]
+    pub markdown: cobalt_config::Markdown,
 }
 
 impl Frontmatter {
@@ -50,6 +53,7 @@ impl Frontmatter {
             collection,
             data,
             pagination,
+            markdown,
         } = config;
 
         let collection = collection.unwrap_or_default();
@@ -61,6 +65,7 @@ impl Frontmatter {
                 anyhow::bail!("Empty strings are not allowed in tags");
             }
         }
+        crate::math::ensure_supported(&markdown)?;
         let fm = Frontmatter {
             pagination: pagination
                 .and_then(|p| pagination::PaginationConfig::from_config(p, &permalink)),
@@ -73,7 +78,7 @@ impl Frontmatter {
             tags: tags.unwrap_or_default(),
             excerpt_separator: excerpt_separator.unwrap_or_else(|| "\n\n".into()),
             published_date,
-            format: format.unwrap_or_default(),
+            format,
             #[cfg(feature = "preview_unstable")]
             templated: templated.unwrap_or(false),
             #[cfg(not(feature = "preview_unstable"))]
@@ -83,6 +88,7 @@ impl Frontmatter {
             weight: weight.unwrap_or(0),
             collection,
             data,
+            markdown,
         };
 
         if let Some(pagination) = &fm.pagination {
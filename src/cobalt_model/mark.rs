@@ -2,12 +2,15 @@ use pulldown_cmark as cmark;
 use serde::Serialize;
 
 use crate::error::Result;
+use crate::math;
 use crate::syntax_highlight::decorate_markdown;
+use crate::toc;
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct MarkdownBuilder {
     pub theme: Option<liquid::model::KString>,
+    pub extensions: cobalt_config::Markdown,
     #[serde(skip)]
     pub syntax: std::sync::Arc<crate::SyntaxHighlight>,
 }
@@ -16,6 +19,7 @@ impl MarkdownBuilder {
     pub fn build(self) -> Markdown {
         Markdown {
             theme: self.theme,
+            extensions: self.extensions,
             syntax: self.syntax,
         }
     }
@@ -24,21 +28,182 @@ impl MarkdownBuilder {
 #[derive(Debug, Clone)]
 pub struct Markdown {
     theme: Option<liquid::model::KString>,
+    extensions: cobalt_config::Markdown,
     syntax: std::sync::Arc<crate::SyntaxHighlight>,
 }
 
 impl Markdown {
     pub fn parse(&self, content: &str) -> Result<String> {
+        self.parse_with(content, &cobalt_config::Markdown::default())
+    }
+
+    /// Parse with per-document `overrides` of the site's extensions
+    pub fn parse_with(&self, content: &str, overrides: &cobalt_config::Markdown) -> Result<String> {
+        let (html, _toc) = self.parse_with_toc(content, overrides, "inline Markdown")?;
+        Ok(html)
+    }
+
+    /// Parse, also returning the table of contents for `page.toc`
+    ///
+    /// `source` names the content in warnings.
+    pub(crate) fn parse_with_toc(
+        &self,
+        content: &str,
+        overrides: &cobalt_config::Markdown,
+        source: &str,
+    ) -> Result<(String, liquid::model::Value)> {
+        let extensions = self.extensions(overrides);
+        let parser = self.events(content, &extensions);
+        self.render_events(parser, &extensions, source)
+    }
+
+    /// Parse `content` into events for [`Markdown::render_events`]
+    pub(crate) fn events<'a>(
+        &self,
+        content: &'a str,
+        extensions: &cobalt_config::Markdown,
+    ) -> cmark::Parser<'a> {
+        cmark::Parser::new_ext(content, options(extensions))
+    }
+
+    /// Render already-parsed `events` to HTML, highlighting code blocks, numbering headings and
+    /// converting math the same way for every source format
+    pub(crate) fn render_events<'a>(
+        &'a self,
+        events: impl Iterator<Item = cmark::Event<'a>>,
+        extensions: &cobalt_config::Markdown,
+        source: &str,
+    ) -> Result<(String, liquid::model::Value)> {
         let mut buf = String::new();
-        let options = cmark::Options::ENABLE_FOOTNOTES
-            | cmark::Options::ENABLE_TABLES
-            | cmark::Options::ENABLE_STRIKETHROUGH
-            | cmark::Options::ENABLE_TASKLISTS;
-        let parser = cmark::Parser::new_ext(content, options);
-        cmark::html::push_html(
-            &mut buf,
-            decorate_markdown(parser, self.syntax.clone(), self.theme.as_deref())?,
+        let events = decorate_markdown(events, self.syntax.clone(), self.theme.as_deref())?;
+        let toc = if extensions
+            .heading_ids
+            .or(extensions.heading_anchors)
+            .unwrap_or(false)
+        {
+            let mut events =
+                toc::HeadingIds::new(events, extensions.heading_anchors.unwrap_or(false));
+            cmark::html::push_html(&mut buf, math::MathToMathml::new(events.by_ref(), source));
+            toc::to_value(
+                &events.into_headings(),
+                extensions.toc_min_level.unwrap_or(1),
+                extensions.toc_max_level.unwrap_or(6),
+            )
+        } else {
+            cmark::html::push_html(&mut buf, math::MathToMathml::new(events, source));
+            liquid::model::Value::Array(Vec::new())
+        };
+        Ok((buf, toc))
+    }
+
+    /// The site's extensions with per-document `overrides` applied
+    pub(crate) fn extensions(
+        &self,
+        overrides: &cobalt_config::Markdown,
+    ) -> cobalt_config::Markdown {
+        overrides.clone().merge(&self.extensions)
+    }
+}
+
+fn options(extensions: &cobalt_config::Markdown) -> cmark::Options {
+    let flags = [
+        (extensions.footnotes, true, cmark::Options::ENABLE_FOOTNOTES),
+        (extensions.tables, true, cmark::Options::ENABLE_TABLES),
+        (
+            extensions.strikethrough,
+            true,
+            cmark::Options::ENABLE_STRIKETHROUGH,
+        ),
+        (extensions.tasklists, true, cmark::Options::ENABLE_TASKLISTS),
+        (
+            extensions.smart_punctuation,
+            false,
+            cmark::Options::ENABLE_SMART_PUNCTUATION,
+        ),
+        (
+            extensions.heading_attributes,
+            false,
+            cmark::Options::ENABLE_HEADING_ATTRIBUTES,
+        ),
+        (
+            extensions.definition_lists,
+            false,
+            cmark::Options::ENABLE_DEFINITION_LIST,
+        ),
+        (extensions.alerts, false, cmark::Options::ENABLE_GFM),
+        (extensions.math, false, cmark::Options::ENABLE_MATH),
+        (
+            extensions.superscript,
+            false,
+            cmark::Options::ENABLE_SUPERSCRIPT,
+        ),
+        (
+            extensions.subscript,
+            false,
+            cmark::Options::ENABLE_SUBSCRIPT,
+        ),
+        (
+            extensions.wikilinks,
+            false,
+            cmark::Options::ENABLE_WIKILINKS,
+        ),
+    ];
+    flags
+        .into_iter()
+        .filter(|(enabled, default, _)| enabled.unwrap_or(*default))
+        .fold(cmark::Options::empty(), |options, (_, _, flag)| {
+            options | flag
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn markdown(extensions: cobalt_config::Markdown) -> Markdown {
+        MarkdownBuilder {
+            theme: None,
+            extensions,
+            syntax: std::sync::Arc::new(crate::SyntaxHighlight::new()),
+        }
+        .build()
+    }
+
+    #[test]
+    fn default_extensions() {
+        let markdown = markdown(Default::default());
+        assert_eq!(
+            markdown.parse("~~gone~~ \"quoted\"").unwrap(),
+            "<p><del>gone</del> \"quoted\"</p>\n"
+        );
+    }
+
+    #[test]
+    fn site_extensions() {
+        let markdown = markdown(cobalt_config::Markdown {
+            smart_punctuation: Some(true),
+            superscript: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(
+            markdown.parse("\"x ^2^\"").unwrap(),
+            "<p>\u{201c}x <sup>2</sup>\u{201d}</p>\n"
+        );
+    }
+
+    #[test]
+    fn document_overrides_site() {
+        let markdown = markdown(cobalt_config::Markdown {
+            smart_punctuation: Some(true),
+            ..Default::default()
+        });
+        let overrides = cobalt_config::Markdown {
+            smart_punctuation: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            markdown.parse_with("\"a\"", &overrides).unwrap(),
+            "<p>\"a\"</p>\n"
         );
-        Ok(buf)
     }
 }
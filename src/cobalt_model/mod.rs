@@ -1,6 +1,8 @@
 mod assets;
+mod bundle;
 mod collection;
 mod config;
+mod css;
 mod frontmatter;
 mod mark;
 mod sass;
@@ -24,6 +26,8 @@ pub use self::assets::Assets;
 pub use self::assets::AssetsBuilder;
 pub use self::collection::Collection;
 pub use self::config::Config;
+pub use self::css::CssBuilder;
+pub use self::css::CssTransform;
 pub use self::frontmatter::Frontmatter;
 pub use self::mark::Markdown;
 pub use self::mark::MarkdownBuilder;
@@ -3,6 +3,7 @@ use std::path;
 
 use serde::{Deserialize, Serialize};
 
+use super::css::CssTransform;
 use super::files;
 use crate::cobalt_model::Minify;
 use crate::error::Result;
@@ -12,6 +13,7 @@ pub(crate) use cobalt_config::SassOutputStyle;
 #[serde(deny_unknown_fields, default)]
 pub struct SassBuilder {
     pub import_dir: path::PathBuf,
+    pub load_paths: Vec<path::PathBuf>,
     pub style: SassOutputStyle,
 }
 
@@ -20,18 +22,32 @@ impl SassBuilder {
         Self {
             style: config.style,
             import_dir: source.join(config.import_dir),
+            load_paths: config
+                .load_paths
+                .iter()
+                .map(|load_path| source.join(load_path.as_path()))
+                .collect(),
         }
     }
 
     pub fn build(self) -> SassCompiler {
-        let Self { import_dir, style } = self;
-        SassCompiler { import_dir, style }
+        let Self {
+            import_dir,
+            load_paths,
+            style,
+        } = self;
+        SassCompiler {
+            import_dir,
+            load_paths,
+            style,
+        }
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct SassCompiler {
     import_dir: path::PathBuf,
+    load_paths: Vec<path::PathBuf>,
     style: SassOutputStyle,
 }
 
@@ -41,9 +57,16 @@ impl SassCompiler {
         source: S,
         dest: D,
         file_path: F,
+        css: &CssTransform,
         minify: &Minify,
     ) -> Result<()> {
-        self.compile_sass_internal(source.as_ref(), dest.as_ref(), file_path.as_ref(), minify)
+        self.compile_sass_internal(
+            source.as_ref(),
+            dest.as_ref(),
+            file_path.as_ref(),
+            css,
+            minify,
+        )
     }
 
     #[cfg(feature = "sass")]
@@ -52,6 +75,7 @@ impl SassCompiler {
         source: &path::Path,
         dest: &path::Path,
         file_path: &path::Path,
+        css: &CssTransform,
         minify: &Minify,
     ) -> Result<()> {
         let sass_opts = grass::Options::default()
@@ -61,7 +85,8 @@ impl SassCompiler {
                     grass::OutputStyle::Compressed
                 }
             })
-            .load_path(&self.import_dir);
+            .load_path(&self.import_dir)
+            .load_paths(&self.load_paths);
         let content = if let Some(file_path) = file_path.to_str() {
             grass::from_path(file_path, &sass_opts)?
         } else {
@@ -75,19 +100,24 @@ impl SassCompiler {
         let mut dest_file = dest.join(rel_src);
         dest_file.set_extension("css");
 
-        #[cfg(feature = "html-minifier")]
-        let content = if minify.css {
-            use html_minifier::css::minify;
-            minify(&content)
-                .map_err(|e| {
-                    anyhow::format_err!(
-                        "Could not minify saas file {} error {}",
-                        source.to_string_lossy(),
-                        e
-                    )
-                })?
-                .to_string()
+        let content = if css.is_enabled() {
+            css.transform(file_path, &content, minify.css)?
         } else {
+            #[cfg(feature = "html-minifier")]
+            let content = if minify.css {
+                use html_minifier::css::minify;
+                minify(&content)
+                    .map_err(|e| {
+                        anyhow::format_err!(
+                            "Could not minify saas file {} error {}",
+                            source.to_string_lossy(),
+                            e
+                        )
+                    })?
+                    .to_string()
+            } else {
+                content
+            };
             content
         };
 
@@ -100,6 +130,7 @@ impl SassCompiler {
         source: &path::Path,
         dest: &path::Path,
         file_path: &path::Path,
+        _css: &CssTransform,
         minify: &Minify,
     ) -> Result<()> {
         let rel_src = file_path
@@ -22,6 +22,7 @@ pub struct Site {
     pub description: Option<liquid::model::KString>,
     pub base_url: Option<liquid::model::KString>,
     pub sitemap: Option<cobalt_config::RelPath>,
+    pub robots: Option<cobalt_config::Robots>,
     pub data: Option<liquid::Object>,
     pub data_dir: &'static str,
     /// The time at which the `cobalt` binary built the site
@@ -35,6 +36,7 @@ impl Site {
             description,
             base_url,
             sitemap,
+            robots,
             data,
             data_dir,
         } = config;
@@ -53,6 +55,7 @@ impl Site {
             description,
             base_url,
             sitemap,
+            robots,
             data,
             data_dir,
             time: DateTime::now(),
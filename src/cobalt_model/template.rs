@@ -1,8 +1,12 @@
 use std::fmt;
+use std::hash::{Hash as _, Hasher};
 use std::path;
 
 use super::files;
+use super::mark;
 use crate::error::Result;
+use crate::filters;
+use crate::shortcode;
 use crate::syntax_highlight;
 use liquid;
 use log::warn;
@@ -13,15 +17,37 @@ use serde::Serialize;
 #[serde(deny_unknown_fields)]
 pub struct LiquidBuilder {
     pub includes_path: path::PathBuf,
+    pub shortcodes_path: path::PathBuf,
+    /// What `include_code` paths are relative to
+    pub source_path: path::PathBuf,
+    /// Where `sri_hash` reads built assets from
+    pub destination_path: path::PathBuf,
+    /// Output paths of `assets.bundles`, by bundle name, for `bundle_url`
+    pub bundles: std::collections::BTreeMap<liquid::model::KString, liquid::model::KString>,
     pub theme: Option<liquid::model::KString>,
     #[serde(skip)]
     pub syntax: std::sync::Arc<crate::SyntaxHighlight>,
+    pub markdown: mark::MarkdownBuilder,
 }
 
 impl LiquidBuilder {
     pub fn build(self) -> Result<Liquid> {
+        // Everything baked into compiled templates: these settings, the syntaxes and themes, and
+        // below, the includes and shortcodes
+        let mut fingerprint = std::hash::DefaultHasher::new();
+        serde_json::to_vec(&self)?.hash(&mut fingerprint);
+        syntax_highlight::fingerprint(&self.syntax).hash(&mut fingerprint);
+        syntax_highlight::fingerprint(&self.markdown.syntax).hash(&mut fingerprint);
+
+        let include_code = syntax_highlight::IncludeCodeParser::new(
+            self.syntax.clone(),
+            self.theme.clone(),
+            self.source_path,
+        );
         let highlight = syntax_highlight::CodeBlockParser::new(self.syntax, self.theme)?;
         let highlight: Box<dyn liquid_core::ParseBlock> = Box::new(highlight);
+        let partials = load_partials_from_path(self.includes_path, &mut fingerprint)?;
+        let shortcodes = load_shortcodes_from_path(self.shortcodes_path, &mut fingerprint)?;
         let parser = liquid::ParserBuilder::with_stdlib()
             .filter(liquid_lib::extra::DateInTz)
             .filter(liquid_lib::shopify::Pluralize)
@@ -32,16 +58,34 @@ impl LiquidBuilder {
             .filter(liquid_lib::jekyll::Shift)
             .filter(liquid_lib::jekyll::Unshift)
             .filter(liquid_lib::jekyll::ArrayToSentenceString)
-            .partials(load_partials_from_path(self.includes_path)?)
+            .filter(filters::RelativeUrl)
+            .filter(filters::AbsoluteUrl)
+            .filter(filters::BundleUrl::new(std::sync::Arc::new(self.bundles)))
+            .filter(filters::SriHash::new(&self.destination_path))
+            .filter(filters::Markdownify::new(self.markdown.build()))
+            .filter(filters::Jsonify)
+            .filter(filters::XmlEscape)
+            .filter(filters::DateToXmlschema)
+            .filter(filters::DateToRfc822)
+            .filter(filters::WhereExp)
+            .filter(filters::GroupBy)
+            .filter(filters::NumberOfWords)
+            .tag(shortcode::ShortcodeParser::new(shortcodes.clone()))
+            .tag(include_code)
+            .partials(partials)
             .block(highlight)
+            .block(shortcode::ShortcodeBlockParser::new(shortcodes))
             .build()?;
-        Ok(Liquid { parser })
+        Ok(Liquid {
+            parser,
+            fingerprint: fingerprint.finish(),
+        })
     }
 }
 
 type Partials = liquid::partials::EagerCompiler<liquid::partials::InMemorySource>;
 
-fn load_partials_from_path(root: path::PathBuf) -> Result<Partials> {
+fn load_partials_from_path(root: path::PathBuf, fingerprint: &mut impl Hasher) -> Result<Partials> {
     let mut source = Partials::empty();
 
     debug!("Loading snippets from `{}`", root.display());
@@ -58,6 +102,7 @@ fn load_partials_from_path(root: path::PathBuf) -> Result<Partials> {
         trace!("Loading snippet `{rel_path}`");
         match files::read_file(file_path) {
             Ok(content) => {
+                (&rel_path, &content).hash(fingerprint);
                 source.add(rel_path, content);
             }
             Err(err) => {
@@ -68,8 +113,48 @@ fn load_partials_from_path(root: path::PathBuf) -> Result<Partials> {
     Ok(source)
 }
 
+fn load_shortcodes_from_path(
+    root: path::PathBuf,
+    fingerprint: &mut impl Hasher,
+) -> Result<shortcode::ShortcodeMap> {
+    let mut shortcodes = shortcode::Shortcodes::default();
+
+    debug!("Loading shortcodes from `{}`", root.display());
+    let template_files = files::FilesBuilder::new(root)?
+        .ignore_hidden(false)?
+        .build()?;
+    for file_path in template_files.files() {
+        let rel_path = file_path
+            .strip_prefix(template_files.root())
+            .expect("file was found under the root");
+        let name = rel_path
+            .with_extension("")
+            .to_str()
+            .expect("only UTF-8 characters supported in paths")
+            .replace('\\', "/");
+        let rel_path = rel_path
+            .to_str()
+            .expect("only UTF-8 characters supported in paths")
+            .to_owned();
+        trace!("Loading shortcode `{name}`");
+        match files::read_file(file_path) {
+            Ok(content) => {
+                (&name, &content).hash(fingerprint);
+                if !shortcodes.insert(name.clone().into(), content) {
+                    warn!("Shortcode `{name}` is defined multiple times, ignoring {rel_path}");
+                }
+            }
+            Err(err) => {
+                warn!("Ignoring shortcode {rel_path}: {err}");
+            }
+        }
+    }
+    Ok(std::sync::Arc::new(shortcodes))
+}
+
 pub struct Liquid {
     parser: liquid::Parser,
+    fingerprint: u64,
 }
 
 impl Liquid {
@@ -77,6 +162,12 @@ impl Liquid {
         let template = self.parser.parse(template)?;
         Ok(template)
     }
+
+    /// Identifies the includes, shortcodes and settings that templates from this parser were
+    /// compiled against
+    pub(crate) fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
 }
 
 impl fmt::Debug for Liquid {
@@ -0,0 +1,180 @@
+//! Pre-compressed siblings of text outputs, e.g. `index.html.gz` and `index.html.br`
+
+use std::path;
+
+use crate::error::Result;
+
+/// Outputs worth compressing
+#[cfg(feature = "compress")]
+const TEXT_EXTENSIONS: &[&str] = &["html", "css", "js", "xml", "json", "svg"];
+
+#[cfg(feature = "compress")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+#[cfg(feature = "compress")]
+impl Encoding {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Brotli => "br",
+        }
+    }
+}
+
+/// Compress every text output under `destination` that is at least `min_size` bytes
+///
+/// Siblings of smaller outputs, or in disabled encodings, are removed so a server never prefers
+/// a stale copy.
+#[cfg(feature = "compress")]
+pub(crate) fn compress_outputs(
+    destination: &path::Path,
+    config: &cobalt_config::Compress,
+) -> Result<()> {
+    use anyhow::Context as _;
+
+    if !config.gzip && !config.brotli {
+        return Ok(());
+    }
+
+    let mut count = 0;
+    for path in text_outputs(destination) {
+        let size = std::fs::metadata(&path)?.len();
+        let content = if config.min_size <= size {
+            Some(std::fs::read(&path)?)
+        } else {
+            None
+        };
+        for (encoding, enabled) in [
+            (Encoding::Gzip, config.gzip),
+            (Encoding::Brotli, config.brotli),
+        ] {
+            let sibling = sibling(&path, encoding);
+            match &content {
+                Some(content) if enabled => {
+                    log::trace!("Compressing `{}`", sibling.display());
+                    std::fs::write(&sibling, encode(content, encoding)?)
+                        .with_context(|| format!("Could not write {}", sibling.display()))?;
+                    count += 1;
+                }
+                _ if sibling.exists() => {
+                    std::fs::remove_file(&sibling)
+                        .with_context(|| format!("Could not remove {}", sibling.display()))?;
+                }
+                _ => {}
+            }
+        }
+    }
+    log::debug!("Wrote {count} compressed files");
+    Ok(())
+}
+
+#[cfg(not(feature = "compress"))]
+pub(crate) fn compress_outputs(
+    _destination: &path::Path,
+    config: &cobalt_config::Compress,
+) -> Result<()> {
+    if config.gzip || config.brotli {
+        log::warn!("Compressed outputs require the `compress` feature");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "compress")]
+fn encode(content: &[u8], encoding: Encoding) -> Result<Vec<u8>> {
+    use std::io::Write as _;
+
+    let encoded = match encoding {
+        Encoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(content)?;
+            encoder.finish()?
+        }
+        Encoding::Brotli => {
+            let mut encoded = Vec::new();
+            {
+                let mut encoder = brotli::CompressorWriter::new(&mut encoded, 4096, 11, 22);
+                encoder.write_all(content)?;
+            }
+            encoded
+        }
+    };
+    Ok(encoded)
+}
+
+#[cfg(feature = "compress")]
+fn text_outputs(destination: &path::Path) -> Vec<path::PathBuf> {
+    walkdir::WalkDir::new(destination)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext))
+        })
+        .collect()
+}
+
+#[cfg(feature = "compress")]
+fn sibling(path: &path::Path, encoding: Encoding) -> path::PathBuf {
+    let mut sibling = path.as_os_str().to_owned();
+    sibling.push(".");
+    sibling.push(encoding.extension());
+    sibling.into()
+}
+
+#[cfg(test)]
+#[cfg(feature = "compress")]
+mod test {
+    use std::io::Read as _;
+
+    use super::*;
+
+    fn config(gzip: bool, brotli: bool) -> cobalt_config::Compress {
+        cobalt_config::Compress {
+            gzip,
+            brotli,
+            min_size: 100,
+        }
+    }
+
+    #[test]
+    fn compress_text_outputs() {
+        let destination = tempfile::tempdir().unwrap();
+        let root = destination.path();
+        let page = "<p>Hello</p>\n".repeat(20);
+        std::fs::create_dir_all(root.join("posts")).unwrap();
+        std::fs::write(root.join("posts/index.html"), &page).unwrap();
+        std::fs::write(root.join("small.css"), "a{}").unwrap();
+        std::fs::write(root.join("image.png"), page.as_bytes()).unwrap();
+
+        compress_outputs(root, &config(true, true)).unwrap();
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&std::fs::read(root.join("posts/index.html.gz")).unwrap()[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, page);
+        let mut decoded = String::new();
+        brotli::Decompressor::new(
+            &std::fs::read(root.join("posts/index.html.br")).unwrap()[..],
+            4096,
+        )
+        .read_to_string(&mut decoded)
+        .unwrap();
+        assert_eq!(decoded, page);
+        assert!(!root.join("small.css.gz").exists());
+        assert!(!root.join("image.png.gz").exists());
+
+        // Disabling an encoding removes its stale copies
+        compress_outputs(root, &config(true, false)).unwrap();
+        assert!(root.join("posts/index.html.gz").exists());
+        assert!(!root.join("posts/index.html.br").exists());
+    }
+}
@@ -1,15 +1,13 @@
 use std::clone::Clone;
-use std::collections::HashMap;
 use std::default::Default;
 use std::path::Path;
-use std::sync::LazyLock;
+use std::sync::Arc;
 
 use anyhow::Context as _;
 use liquid::Object;
 use liquid::ValueView;
 use liquid::model::Value;
 use log::trace;
-use regex::Regex;
 use rss::Category;
 
 use crate::cobalt_model;
@@ -18,11 +16,15 @@ use crate::cobalt_model::files;
 use crate::cobalt_model::permalink;
 use crate::cobalt_model::slug;
 use crate::error::Result;
+use crate::format::Formats;
+use crate::format::Rendered;
+use crate::format::Renderer;
+use crate::globals::Globals;
+use crate::layout::LayoutCache;
 
 pub(crate) struct RenderContext<'a> {
     pub(crate) parser: &'a cobalt_model::Liquid,
-    pub(crate) markdown: &'a cobalt_model::Markdown,
-    pub(crate) globals: &'a Object,
+    pub(crate) globals: Globals<'a>,
     pub(crate) minify: Minify,
 }
 
@@ -33,6 +35,7 @@ pub(crate) struct Document {
     pub(crate) content: liquid::model::KString,
     pub(crate) attributes: Object,
     pub(crate) front: cobalt_model::Frontmatter,
+    renderer: Arc<dyn Renderer>,
 }
 
 impl Document {
@@ -40,14 +43,15 @@ impl Document {
         src_path: &Path,
         rel_path: &relative_path::RelativePath,
         default_front: cobalt_config::Frontmatter,
+        formats: &Formats,
     ) -> Result<Document> {
         trace!("Parsing `{rel_path}`");
         let content = files::read_file(src_path)?;
-        let builder = cobalt_config::Document::parse(&content)?;
-        let (front, content) = builder.into_parts();
+        let (front, content) = formats.get(None, rel_path).split(&content)?;
         let front = front.merge_path(rel_path).merge(&default_front);
 
         let front = cobalt_model::Frontmatter::from_config(front)?;
+        let renderer = formats.get(front.format, rel_path);
 
         let (file_path, url_path) = {
             let perma_attributes = permalink_attributes(&front, rel_path);
@@ -68,6 +72,7 @@ impl Document {
             content,
             attributes: doc_attributes,
             front,
+            renderer,
         })
     }
 
@@ -174,100 +179,134 @@ impl Document {
     ///
     /// Takes `content` string and returns rendered HTML. This function doesn't
     /// take `"extends"` attribute into account. This function can be used for
-    /// rendering content or excerpt.
-    fn render_html(&self, content: &str, context: &RenderContext<'_>) -> Result<String> {
+    /// rendering content or excerpt.  Formats with headings also produce a table of contents.
+    fn render_html(
+        &self,
+        content: &str,
+        renderer: &dyn Renderer,
+        context: &RenderContext<'_>,
+    ) -> Result<Rendered> {
         let html = if self.front.templated {
             let template = context.parser.parse(content)?;
-            template.render(context.globals)?
+            template.render(&context.globals)?
         } else {
             content.to_owned()
         };
 
-        let html = match self.front.format {
-            cobalt_model::SourceFormat::Raw => html,
-            cobalt_model::SourceFormat::Markdown => context.markdown.parse(&html)?,
-        };
-
-        Ok(html)
+        renderer.render(&html, &self.front, &self.file_path)
     }
 
-    /// Renders the excerpt and adds it to attributes of the document.
+    /// Renders the excerpt, to be added to the attributes of the document.
     ///
     /// The excerpt is either taken from the `excerpt` frontmatter setting, if
     /// given, or extracted from the content, if `excerpt_separator` is not
     /// empty. When neither condition applies, the excerpt is set to the `Nil`
     /// value.
-    pub(crate) fn render_excerpt(&mut self, context: &RenderContext<'_>) -> Result<()> {
+    pub(crate) fn render_excerpt(&self, context: &RenderContext<'_>) -> Result<Value> {
         let value = if let Some(excerpt_str) = self.front.excerpt.as_ref() {
-            let excerpt = self.render_html(excerpt_str, context)?;
-            Value::scalar(excerpt)
+            let renderer = self
+                .renderer
+                .excerpt_renderer()
+                .unwrap_or(self.renderer.as_ref());
+            let excerpt = self.render_html(excerpt_str, renderer, context)?;
+            Value::scalar(excerpt.html)
         } else if self.front.excerpt_separator.is_empty() {
             Value::Nil
         } else {
-            let excerpt = extract_excerpt(
-                &self.content,
-                self.front.format,
-                &self.front.excerpt_separator,
-            );
-            let excerpt = self.render_html(&excerpt, context)?;
-            Value::scalar(excerpt)
+            let excerpt = self
+                .renderer
+                .excerpt(&self.content, &self.front.excerpt_separator);
+            let excerpt = self.render_html(&excerpt, self.renderer.as_ref(), context)?;
+            Value::scalar(excerpt.html)
         };
 
-        self.attributes.insert("excerpt".into(), value);
-        Ok(())
+        Ok(value)
     }
 
-    /// Renders the content and adds it to attributes of the document.
+    /// Renders the content, its table of contents and any attachments, to be added to the
+    /// attributes of the document.
     ///
     /// When we say "content" we mean only this document without extended layout.
-    pub(crate) fn render_content(&mut self, context: &RenderContext<'_>) -> Result<()> {
-        let content_html = self.render_html(&self.content, context)?;
-        self.attributes
-            .insert("content".into(), Value::scalar(content_html));
-        Ok(())
+    pub(crate) fn render_content(&self, context: &RenderContext<'_>) -> Result<Rendered> {
+        self.render_html(&self.content, self.renderer.as_ref(), context)
     }
 
     /// Renders the document to an HTML string.
     ///
-    /// Side effects:
-    ///
-    /// * layout may be inserted to layouts cache
+    /// Layouts are applied from the inside out: each layout's output becomes `page.content` for
+    /// the layout it extends.  A layout's frontmatter is available as `layout.*`, with values
+    /// from inner layouts taking precedence.
     pub(crate) fn render(
-        &mut self,
+        &self,
         context: &RenderContext<'_>,
-        layouts: &HashMap<String, String>,
+        layouts: &LayoutCache,
     ) -> Result<String> {
+        let mut content_html = context
+            .globals
+            .page
+            .get("content")
+            .ok_or_else(|| anyhow::format_err!("Internal error: page content isn't rendered"))?
+            .render()
+            .to_string();
+
         if let Some(ref layout) = self.front.layout {
-            let layout_data_ref = layouts.get(layout.as_str()).ok_or_else(|| {
-                anyhow::format_err!(
-                    "Layout {} does not exist (referenced in {}).",
-                    layout,
-                    self.file_path
-                )
-            })?;
-
-            let template = context
-                .parser
-                .parse(layout_data_ref)
-                .with_context(|| anyhow::format_err!("Failed to parse layout `{}`", layout))?;
-            let content_html = template
-                .render(context.globals)
-                .with_context(|| anyhow::format_err!("Failed to render layout `{}`", layout))?;
-            let content_html = minify_if_enabled(content_html, context, &self.file_path)?;
-            Ok(content_html)
-        } else {
-            let path = &[
-                liquid::model::KStringCow::from_static("page").into(),
-                liquid::model::KStringCow::from_static("content").into(),
-            ];
-            let content_html = liquid::model::try_find(context.globals, path)
-                .ok_or_else(|| anyhow::format_err!("Internal error: page isn't in globals"))?
-                .render()
-                .to_string();
-
-            let content_html = minify_if_enabled(content_html, context, &self.file_path)?;
-            Ok(content_html)
+            // Only copied when a layout extends another and needs its own `page.content`
+            let mut parent_page: Option<Object> = None;
+            let mut layout_vars = Object::new();
+            let mut chain: Vec<&str> = Vec::new();
+            let mut next = Some(layout.as_str());
+            while let Some(name) = next {
+                if chain.contains(&name) {
+                    anyhow::bail!(
+                        "Layout cycle detected: {} -> {} (referenced in {}).",
+                        chain.join(" -> "),
+                        name,
+                        self.file_path
+                    );
+                }
+                let layout = layouts.get(name).ok_or_else(|| match chain.last() {
+                    Some(child) => anyhow::format_err!(
+                        "Layout {} does not exist (referenced in layout {}).",
+                        name,
+                        child
+                    ),
+                    None => anyhow::format_err!(
+                        "Layout {} does not exist (referenced in {}).",
+                        name,
+                        self.file_path
+                    ),
+                })?;
+
+                for (key, value) in layout.front().iter() {
+                    layout_vars
+                        .entry(key.clone())
+                        .or_insert_with(|| value.clone());
+                }
+                if !chain.is_empty() {
+                    parent_page
+                        .get_or_insert_with(|| context.globals.page.clone())
+                        .insert("content".into(), Value::scalar(content_html));
+                }
+                let globals = Globals {
+                    page: parent_page.as_ref().unwrap_or(context.globals.page),
+                    layout: Some(&layout_vars),
+                    ..context.globals
+                };
+
+                let template = layout
+                    .template()
+                    .with_context(|| anyhow::format_err!("Failed to parse layout `{}`", name))?;
+                content_html = template
+                    .render(&globals)
+                    .with_context(|| anyhow::format_err!("Failed to render layout `{}`", name))?;
+
+                chain.push(name);
+                next = layout.layout();
+            }
         }
+
+        let content_html = minify_if_enabled(content_html, context, &self.file_path)?;
+        Ok(content_html)
     }
 }
 
@@ -412,39 +451,3 @@ fn minify_if_enabled(
         Ok(html)
     }
 }
-
-fn extract_excerpt_raw(content: &str, excerpt_separator: &str) -> String {
-    content
-        .split(excerpt_separator)
-        .next()
-        .unwrap_or(content)
-        .to_owned()
-}
-
-fn extract_excerpt_markdown(content: &str, excerpt_separator: &str) -> String {
-    static MARKDOWN_REF: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"(?m:^ {0,3}\[[^\]]+\]:.+$)").unwrap());
-
-    let mut trail = String::new();
-
-    if MARKDOWN_REF.is_match(content) {
-        for mat in MARKDOWN_REF.find_iter(content) {
-            trail.push_str(mat.as_str());
-            trail.push('\n');
-        }
-    }
-    trail + content.split(excerpt_separator).next().unwrap_or(content)
-}
-
-fn extract_excerpt(
-    content: &str,
-    format: cobalt_model::SourceFormat,
-    excerpt_separator: &str,
-) -> String {
-    match format {
-        cobalt_model::SourceFormat::Markdown => {
-            extract_excerpt_markdown(content, excerpt_separator)
-        }
-        cobalt_model::SourceFormat::Raw => extract_excerpt_raw(content, excerpt_separator),
-    }
-}
@@ -0,0 +1,808 @@
+//! Jekyll-compatible filters that depend on Cobalt's own context
+
+use std::sync::LazyLock;
+
+use liquid_core::Expression;
+use liquid_core::Language;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::model::KString;
+use liquid_core::model::ScalarCow;
+use liquid_core::runtime::StackFrame;
+use liquid_core::{
+    Display_filter, Filter, FilterParameters, FilterReflection, FromFilterParameters, ParseFilter,
+};
+use liquid_core::{Value, ValueView};
+
+use crate::cobalt_model::Markdown;
+use crate::integrity::Algorithm;
+
+fn invalid_input<S: Into<KString>>(cause: S) -> liquid_core::Error {
+    liquid_core::Error::with_msg("Invalid input").context("cause", cause)
+}
+
+fn invalid_argument(argument: &'static str, cause: impl std::fmt::Display) -> liquid_core::Error {
+    liquid_core::Error::with_msg("Invalid argument")
+        .context("argument", argument)
+        .context("cause", cause.to_string())
+}
+
+fn base_url(runtime: &dyn Runtime) -> Option<String> {
+    let path = [ScalarCow::new("site"), ScalarCow::new("base_url")];
+    runtime
+        .try_get(&path)
+        .map(|url| url.to_kstr().trim_end_matches('/').to_owned())
+}
+
+fn is_absolute_url(url: &str) -> bool {
+    url.contains("://") || url.starts_with("//")
+}
+
+/// The path portion of `site.base_url`, e.g. `/blog` for `https://example.com/blog`
+fn base_path(base_url: &str) -> &str {
+    let without_scheme = base_url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(base_url);
+    without_scheme
+        .find('/')
+        .map(|i| &without_scheme[i..])
+        .unwrap_or("")
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "relative_url",
+    description = "Prefix a path with the path of `site.base_url`.",
+    parsed(RelativeUrlFilter)
+)]
+pub(crate) struct RelativeUrl;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "relative_url"]
+struct RelativeUrlFilter;
+
+impl Filter for RelativeUrlFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let input = input.to_kstr();
+        if is_absolute_url(&input) {
+            return Ok(Value::scalar(input.into_owned()));
+        }
+        let base_url = base_url(runtime).unwrap_or_default();
+        let url = format!("{}/{}", base_path(&base_url), input.trim_start_matches('/'));
+        Ok(Value::scalar(url))
+    }
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "absolute_url",
+    description = "Prefix a path with `site.base_url`.",
+    parsed(AbsoluteUrlFilter)
+)]
+pub(crate) struct AbsoluteUrl;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "absolute_url"]
+struct AbsoluteUrlFilter;
+
+impl Filter for AbsoluteUrlFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let input = input.to_kstr();
+        if is_absolute_url(&input) {
+            return Ok(Value::scalar(input.into_owned()));
+        }
+        let base_url = base_url(runtime).unwrap_or_default();
+        let url = format!("{}/{}", base_url, input.trim_start_matches('/'));
+        Ok(Value::scalar(url))
+    }
+}
+
+/// Output paths of `assets.bundles`, by bundle name
+pub(crate) type Bundles = std::sync::Arc<std::collections::BTreeMap<KString, KString>>;
+
+/// Resolves a bundle's name to its URL, relative to `site.base_url` like `relative_url`
+#[derive(Clone)]
+pub(crate) struct BundleUrl {
+    bundles: Bundles,
+}
+
+impl BundleUrl {
+    pub(crate) fn new(bundles: Bundles) -> Self {
+        Self { bundles }
+    }
+}
+
+impl FilterReflection for BundleUrl {
+    fn name(&self) -> &str {
+        "bundle_url"
+    }
+
+    fn description(&self) -> &str {
+        "Get the URL of a bundle from `assets.bundles`."
+    }
+
+    fn positional_parameters(&self) -> &'static [liquid_core::parser::ParameterReflection] {
+        &[]
+    }
+
+    fn keyword_parameters(&self) -> &'static [liquid_core::parser::ParameterReflection] {
+        &[]
+    }
+}
+
+impl ParseFilter for BundleUrl {
+    fn parse(&self, mut args: liquid_core::parser::FilterArguments<'_>) -> Result<Box<dyn Filter>> {
+        if args.positional.next().is_some() {
+            return Err(
+                liquid_core::Error::with_msg("Invalid number of positional arguments")
+                    .context("cause", "expected at most 0 positional arguments"),
+            );
+        }
+        if let Some(arg) = args.keyword.next() {
+            return Err(liquid_core::Error::with_msg(format!(
+                "Unexpected named argument `{}`",
+                arg.0
+            )));
+        }
+        Ok(Box::new(BundleUrlFilter {
+            bundles: self.bundles.clone(),
+        }))
+    }
+
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+#[derive(Debug, Display_filter)]
+#[name = "bundle_url"]
+struct BundleUrlFilter {
+    bundles: Bundles,
+}
+
+impl Filter for BundleUrlFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let name = input.to_kstr();
+        let output = self.bundles.get(name.as_str()).ok_or_else(|| {
+            liquid_core::Error::with_msg(format!("Unknown bundle `{name}`"))
+                .context("bundles", itertools::join(self.bundles.keys(), ", "))
+        })?;
+        let base_url = base_url(runtime).unwrap_or_default();
+        let url = format!("{}/{output}", base_path(&base_url));
+        Ok(Value::scalar(url))
+    }
+}
+
+/// Hashes a built asset for an `integrity` attribute
+#[derive(Clone)]
+pub(crate) struct SriHash {
+    destination: std::sync::Arc<std::path::Path>,
+}
+
+impl SriHash {
+    pub(crate) fn new(destination: &std::path::Path) -> Self {
+        Self {
+            destination: destination.into(),
+        }
+    }
+}
+
+impl FilterReflection for SriHash {
+    fn name(&self) -> &str {
+        "sri_hash"
+    }
+
+    fn description(&self) -> &str {
+        "Hash a built asset for an `integrity` attribute."
+    }
+
+    fn positional_parameters(&self) -> &'static [liquid_core::parser::ParameterReflection] {
+        &[liquid_core::parser::ParameterReflection {
+            name: "algorithm",
+            description: "`sha256`, `sha384` (the default) or `sha512`",
+            is_optional: true,
+        }]
+    }
+
+    fn keyword_parameters(&self) -> &'static [liquid_core::parser::ParameterReflection] {
+        &[]
+    }
+}
+
+impl ParseFilter for SriHash {
+    fn parse(&self, mut args: liquid_core::parser::FilterArguments<'_>) -> Result<Box<dyn Filter>> {
+        let algorithm = args.positional.next();
+        if args.positional.next().is_some() {
+            return Err(
+                liquid_core::Error::with_msg("Invalid number of positional arguments")
+                    .context("cause", "expected at most 1 positional argument"),
+            );
+        }
+        if let Some(arg) = args.keyword.next() {
+            return Err(liquid_core::Error::with_msg(format!(
+                "Unexpected named argument `{}`",
+                arg.0
+            )));
+        }
+        Ok(Box::new(SriHashFilter {
+            destination: self.destination.clone(),
+            algorithm,
+        }))
+    }
+
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+#[derive(Debug, Display_filter)]
+#[name = "sri_hash"]
+struct SriHashFilter {
+    destination: std::sync::Arc<std::path::Path>,
+    algorithm: Option<Expression>,
+}
+
+impl Filter for SriHashFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let algorithm = match &self.algorithm {
+            Some(algorithm) => {
+                let algorithm = algorithm.evaluate(runtime)?.to_kstr().into_owned();
+                Algorithm::parse(&algorithm).map_err(|e| invalid_argument("algorithm", e))?
+            }
+            None => Algorithm::default(),
+        };
+
+        // Accept what `relative_url` and `bundle_url` return
+        let url = input.to_kstr();
+        let base_url = base_url(runtime).unwrap_or_default();
+        let path = url
+            .strip_prefix(base_path(&base_url))
+            .filter(|path| path.starts_with('/'))
+            .unwrap_or(&url);
+        let rel_path = cobalt_config::RelPath::try_from(path.trim_start_matches('/'))
+            .ok()
+            .filter(|path| !path.as_str().is_empty() && !path.as_str().starts_with(".."))
+            .ok_or_else(|| invalid_input(format!("`{url}` is not a path within the site")))?;
+        let content = std::fs::read(rel_path.to_path(&self.destination))
+            .map_err(|_| invalid_input(format!("`{url}` has not been built")))?;
+        Ok(Value::scalar(algorithm.integrity(&content)))
+    }
+}
+
+/// Renders Markdown with the site's `Markdown` configuration
+#[derive(Clone)]
+pub(crate) struct Markdownify {
+    markdown: Markdown,
+}
+
+impl Markdownify {
+    pub(crate) fn new(markdown: Markdown) -> Self {
+        Self { markdown }
+    }
+}
+
+impl FilterReflection for Markdownify {
+    fn name(&self) -> &str {
+        "markdownify"
+    }
+
+    fn description(&self) -> &str {
+        "Convert a Markdown-formatted string into HTML."
+    }
+
+    fn positional_parameters(&self) -> &'static [liquid_core::parser::ParameterReflection] {
+        &[]
+    }
+
+    fn keyword_parameters(&self) -> &'static [liquid_core::parser::ParameterReflection] {
+        &[]
+    }
+}
+
+impl ParseFilter for Markdownify {
+    fn parse(&self, mut args: liquid_core::parser::FilterArguments<'_>) -> Result<Box<dyn Filter>> {
+        if args.positional.next().is_some() {
+            return Err(
+                liquid_core::Error::with_msg("Invalid number of positional arguments")
+                    .context("cause", "expected at most 0 positional arguments"),
+            );
+        }
+        if let Some(arg) = args.keyword.next() {
+            return Err(liquid_core::Error::with_msg(format!(
+                "Unexpected named argument `{}`",
+                arg.0
+            )));
+        }
+        Ok(Box::new(MarkdownifyFilter {
+            markdown: self.markdown.clone(),
+        }))
+    }
+
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+#[derive(Debug, Display_filter)]
+#[name = "markdownify"]
+struct MarkdownifyFilter {
+    markdown: Markdown,
+}
+
+impl Filter for MarkdownifyFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        let html = self
+            .markdown
+            .parse(&input.to_kstr())
+            .map_err(|e| liquid_core::Error::with_msg(e.to_string()))?;
+        Ok(Value::scalar(html))
+    }
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "jsonify",
+    description = "Convert a value into JSON.",
+    parsed(JsonifyFilter)
+)]
+pub(crate) struct Jsonify;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "jsonify"]
+struct JsonifyFilter;
+
+impl Filter for JsonifyFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        let json = serde_json::to_string(&input.to_value())
+            .map_err(|e| liquid_core::Error::with_msg(e.to_string()))?;
+        Ok(Value::scalar(json))
+    }
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "xml_escape",
+    description = "Escape a string for use in XML.",
+    parsed(XmlEscapeFilter)
+)]
+pub(crate) struct XmlEscape;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "xml_escape"]
+struct XmlEscapeFilter;
+
+impl Filter for XmlEscapeFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        if input.is_nil() {
+            return Ok(Value::Nil);
+        }
+        let input = input.to_kstr();
+        let mut escaped = String::with_capacity(input.len());
+        for c in input.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                c => escaped.push(c),
+            }
+        }
+        Ok(Value::scalar(escaped))
+    }
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "date_to_xmlschema",
+    description = "Format a date in ISO 8601 / RFC 3339.",
+    parsed(DateToXmlschemaFilter)
+)]
+pub(crate) struct DateToXmlschema;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "date_to_xmlschema"]
+struct DateToXmlschemaFilter;
+
+impl Filter for DateToXmlschemaFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        let Some(date) = input.as_scalar().and_then(|s| s.to_date_time()) else {
+            return Ok(input.to_value());
+        };
+        let formatted = (*date)
+            .format(&time::format_description::well_known::Rfc3339)
+            .map_err(|e| liquid_core::Error::with_msg(e.to_string()))?;
+        Ok(Value::scalar(formatted))
+    }
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "date_to_rfc822",
+    description = "Format a date for use in RSS feeds.",
+    parsed(DateToRfc822Filter)
+)]
+pub(crate) struct DateToRfc822;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "date_to_rfc822"]
+struct DateToRfc822Filter;
+
+impl Filter for DateToRfc822Filter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        let Some(date) = input.as_scalar().and_then(|s| s.to_date_time()) else {
+            return Ok(input.to_value());
+        };
+        Ok(Value::scalar(date.to_rfc2822()))
+    }
+}
+
+#[derive(Debug, FilterParameters)]
+struct WhereExpArgs {
+    #[parameter(description = "The name each element is bound to", arg_type = "str")]
+    variable: Expression,
+    #[parameter(
+        description = "The condition, as used in an `if` tag, an element must satisfy",
+        arg_type = "str"
+    )]
+    condition: Expression,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "where_exp",
+    description = "Filter the elements of an array to those satisfying a condition.",
+    parameters(WhereExpArgs),
+    parsed(WhereExpFilter)
+)]
+pub(crate) struct WhereExp;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "where_exp"]
+struct WhereExpFilter {
+    #[parameters]
+    args: WhereExpArgs,
+}
+
+static CONDITION_LANGUAGE: LazyLock<Language> = LazyLock::new(|| {
+    let mut language = Language::default();
+    language
+        .blocks
+        .register("if".to_owned(), liquid_lib::stdlib::IfBlock.into());
+    language
+});
+
+impl Filter for WhereExpFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+
+        let Some(array) = input.as_array() else {
+            return Err(invalid_input("Array expected"));
+        };
+
+        let condition = format!("{{% if {} %}}true{{% endif %}}", args.condition);
+        let condition = liquid_core::parser::parse(&condition, &CONDITION_LANGUAGE)
+            .map(liquid_core::Template::new)?;
+
+        let mut filtered = Vec::new();
+        for item in array.values() {
+            let mut scope = liquid_core::Object::new();
+            scope.insert(args.variable.clone().into_owned(), item.to_value());
+            let scope = StackFrame::new(runtime, &scope);
+            if liquid_core::Renderable::render(&condition, &scope)? == "true" {
+                filtered.push(item.to_value());
+            }
+        }
+        Ok(Value::array(filtered))
+    }
+}
+
+#[derive(Debug, FilterParameters)]
+struct GroupByArgs {
+    #[parameter(description = "The property to group by", arg_type = "str")]
+    property: Expression,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "group_by",
+    description = "Group the elements of an array by a property.",
+    parameters(GroupByArgs),
+    parsed(GroupByFilter)
+)]
+pub(crate) struct GroupBy;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "group_by"]
+struct GroupByFilter {
+    #[parameters]
+    args: GroupByArgs,
+}
+
+impl Filter for GroupByFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+        let property: &str = &args.property;
+
+        let Some(array) = input.as_array() else {
+            return Err(invalid_input("Array expected"));
+        };
+
+        let mut groups: Vec<(String, Vec<Value>)> = Vec::new();
+        for item in array.values() {
+            let name = item
+                .as_object()
+                .and_then(|object| object.get(property))
+                .map(|value| value.to_kstr().to_string())
+                .unwrap_or_default();
+            match groups.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, items)) => items.push(item.to_value()),
+                None => groups.push((name, vec![item.to_value()])),
+            }
+        }
+
+        let groups: Vec<_> = groups
+            .into_iter()
+            .map(|(name, items)| {
+                let group: liquid_core::Object = [
+                    ("name".into(), Value::scalar(name)),
+                    ("size".into(), Value::scalar(items.len() as i64)),
+                    ("items".into(), Value::array(items)),
+                ]
+                .into_iter()
+                .collect();
+                Value::Object(group)
+            })
+            .collect();
+        Ok(Value::array(groups))
+    }
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "number_of_words",
+    description = "Count the number of words in a string.",
+    parsed(NumberOfWordsFilter)
+)]
+pub(crate) struct NumberOfWords;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "number_of_words"]
+struct NumberOfWordsFilter;
+
+impl Filter for NumberOfWordsFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        let count = input.to_kstr().split_whitespace().count();
+        Ok(Value::scalar(count as i64))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str, globals: &liquid::Object) -> String {
+        let parser = liquid::ParserBuilder::with_stdlib()
+            .filter(RelativeUrl)
+            .filter(AbsoluteUrl)
+            .filter(Jsonify)
+            .filter(XmlEscape)
+            .filter(DateToXmlschema)
+            .filter(DateToRfc822)
+            .filter(WhereExp)
+            .filter(GroupBy)
+            .filter(NumberOfWords)
+            .build()
+            .unwrap();
+        parser.parse(template).unwrap().render(globals).unwrap()
+    }
+
+    fn site(base_url: &str) -> liquid::Object {
+        liquid::object!({ "site": { "base_url": base_url } })
+    }
+
+    #[test]
+    fn relative_url_uses_base_path() {
+        let globals = site("https://example.com/blog");
+        assert_eq!(
+            render("{{ '/css/main.css' | relative_url }}", &globals),
+            "/blog/css/main.css"
+        );
+        assert_eq!(
+            render(
+                "{{ 'css/main.css' | relative_url }}",
+                &site("https://example.com")
+            ),
+            "/css/main.css"
+        );
+    }
+
+    #[test]
+    fn absolute_url_uses_base_url() {
+        let globals = site("https://example.com/blog");
+        assert_eq!(
+            render("{{ '/css/main.css' | absolute_url }}", &globals),
+            "https://example.com/blog/css/main.css"
+        );
+        assert_eq!(
+            render("{{ 'https://other.com/x' | absolute_url }}", &globals),
+            "https://other.com/x"
+        );
+    }
+
+    #[test]
+    fn bundle_url_resolves_names() {
+        let bundles = std::collections::BTreeMap::from([("site_js".into(), "js/site.js".into())]);
+        let parser = liquid::ParserBuilder::with_stdlib()
+            .filter(BundleUrl::new(std::sync::Arc::new(bundles)))
+            .build()
+            .unwrap();
+        let globals = site("https://example.com/blog");
+        let render = |template: &str| parser.parse(template).unwrap().render(&globals);
+        assert_eq!(
+            render("{{ 'site_js' | bundle_url }}").unwrap(),
+            "/blog/js/site.js"
+        );
+        let unknown = render("{{ 'site_css' | bundle_url }}").unwrap_err();
+        assert!(
+            unknown.to_string().contains("Unknown bundle `site_css`"),
+            "{unknown}"
+        );
+    }
+
+    #[test]
+    fn sri_hash_reads_built_assets() {
+        let destination = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(destination.path().join("js")).unwrap();
+        std::fs::write(
+            destination.path().join("js/site.js"),
+            "alert('Hello, world.');",
+        )
+        .unwrap();
+        let parser = liquid::ParserBuilder::with_stdlib()
+            .filter(SriHash::new(destination.path()))
+            .build()
+            .unwrap();
+        let globals = site("https://example.com/blog");
+        let render = |template: &str| parser.parse(template).unwrap().render(&globals);
+        assert_eq!(
+            render("{{ '/blog/js/site.js' | sri_hash: 'sha256' }}").unwrap(),
+            "sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng="
+        );
+        assert!(
+            render("{{ 'js/site.js' | sri_hash }}")
+                .unwrap()
+                .starts_with("sha384-")
+        );
+        let missing = render("{{ 'js/missing.js' | sri_hash }}").unwrap_err();
+        assert!(
+            missing.to_string().contains("has not been built"),
+            "{missing}"
+        );
+        assert!(render("{{ '../secret' | sri_hash }}").is_err());
+        assert!(render("{{ 'js/site.js' | sri_hash: 'md5' }}").is_err());
+    }
+
+    #[test]
+    fn jsonify_and_xml_escape() {
+        let globals = liquid::object!({ "data": { "a": [1, "<b>"] } });
+        assert_eq!(
+            render("{{ data | jsonify }}", &globals),
+            r#"{"a":[1,"<b>"]}"#
+        );
+        assert_eq!(
+            render("{{ \"<a href='x'>&</a>\" | xml_escape }}", &globals),
+            "&lt;a href=&#39;x&#39;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+
+    fn markdownify(extensions: cobalt_config::Markdown) -> liquid::Parser {
+        let markdown = crate::cobalt_model::MarkdownBuilder {
+            theme: None,
+            extensions,
+            syntax: std::sync::Arc::new(crate::SyntaxHighlight::new()),
+        }
+        .build();
+        liquid::ParserBuilder::with_stdlib()
+            .filter(Markdownify::new(markdown))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn markdownify_uses_site_options() {
+        let globals = liquid::object!({ "text": "\"Hi\" ~~there~~" });
+        let render = |parser: liquid::Parser, template: &str| {
+            parser.parse(template).unwrap().render(&globals).unwrap()
+        };
+
+        assert_eq!(
+            render(markdownify(Default::default()), "{{ text | markdownify }}"),
+            "<p>\"Hi\" <del>there</del></p>\n"
+        );
+        let extensions = cobalt_config::Markdown {
+            smart_punctuation: Some(true),
+            strikethrough: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            render(markdownify(extensions), "{{ text | markdownify }}"),
+            "<p>\u{201c}Hi\u{201d} ~~there~~</p>\n"
+        );
+        // Content is only Markdown, a leading `---` is not frontmatter
+        assert_eq!(
+            render(
+                markdownify(Default::default()),
+                "{{ \"---\ntitle: Hi\n---\n\" | markdownify }}"
+            ),
+            "<hr />\n<h2>title: Hi</h2>\n"
+        );
+        assert!(
+            markdownify(Default::default())
+                .parse("{{ text | markdownify: 'gfm' }}")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn date_formats() {
+        let globals = liquid::object!({});
+        assert_eq!(
+            render(
+                "{{ '2016-01-01 21:00:00 +0100' | date_to_xmlschema }}",
+                &globals
+            ),
+            "2016-01-01T21:00:00+01:00"
+        );
+        assert_eq!(
+            render(
+                "{{ '2016-01-01 21:00:00 +0100' | date_to_rfc822 }}",
+                &globals
+            ),
+            "Fri, 01 Jan 2016 21:00:00 +0100"
+        );
+    }
+
+    #[test]
+    fn where_exp_filters_items() {
+        let globals = liquid::object!({
+            "min": 2,
+            "posts": [{ "title": "a", "n": 1 }, { "title": "b", "n": 3 }],
+        });
+        assert_eq!(
+            render(
+                "{% assign r = posts | where_exp: 'p', 'p.n >= min' %}{% for p in r %}{{ p.title }}{% endfor %}",
+                &globals
+            ),
+            "b"
+        );
+    }
+
+    #[test]
+    fn group_by_property() {
+        let globals = liquid::object!({
+            "posts": [
+                { "title": "a", "year": 2015 },
+                { "title": "b", "year": 2016 },
+                { "title": "c", "year": 2015 },
+            ],
+        });
+        assert_eq!(
+            render(
+                "{% assign g = posts | group_by: 'year' %}{% for y in g %}{{ y.name }}:{{ y.size }};{% endfor %}",
+                &globals
+            ),
+            "2015:2;2016:1;"
+        );
+    }
+
+    #[test]
+    fn number_of_words_counts() {
+        let globals = liquid::object!({});
+        assert_eq!(
+            render("{{ 'one two  three\nfour' | number_of_words }}", &globals),
+            "4"
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! [Djot](https://djot.net), parsed by `jotdown`
+//!
+//! The parsed document is translated into Markdown's events so code blocks, headings and math
+//! render like they do in Markdown.  Attributes are kept on headings, divs and spans.
+
+use jotdown::Container;
+use pulldown_cmark::CodeBlockKind;
+use pulldown_cmark::CowStr;
+use pulldown_cmark::Event;
+use pulldown_cmark::LinkType;
+use pulldown_cmark::Tag;
+use pulldown_cmark::TagEnd;
+use relative_path::RelativePath;
+
+use super::Rendered;
+use super::Renderer;
+use super::heading_level;
+use crate::cobalt_model;
+use crate::error::Result;
+use crate::toc::escape_attr;
+
+#[derive(Debug)]
+pub(super) struct Djot {
+    markdown: cobalt_model::Markdown,
+}
+
+impl Djot {
+    pub(super) fn new(markdown: cobalt_model::Markdown) -> Self {
+        Self { markdown }
+    }
+}
+
+impl Renderer for Djot {
+    fn render(
+        &self,
+        content: &str,
+        front: &cobalt_model::Frontmatter,
+        file_path: &RelativePath,
+    ) -> Result<Rendered> {
+        let extensions = self.markdown.extensions(&front.markdown);
+        self.markdown
+            .render_events(events(content).into_iter(), &extensions, file_path.as_str())
+            .map(Rendered::new)
+    }
+}
+
+/// Where the text inside a container goes
+enum Text {
+    Markup,
+    /// Code, verbatim and math, which are a single event in Markdown
+    Literal(String),
+    Html {
+        block: bool,
+    },
+    Dropped,
+}
+
+fn events(content: &str) -> Vec<Event<'_>> {
+    let mut events = Vec::new();
+    let mut text = Text::Markup;
+    // Whether each open list is tight
+    let mut lists: Vec<bool> = Vec::new();
+    // Whether paragraphs directly inside each open block container are left out
+    let mut bare: Vec<bool> = Vec::new();
+    let mut section = jotdown::Attributes::new();
+    let mut definitions = 0;
+
+    for event in jotdown::Parser::new(content) {
+        match event {
+            jotdown::Event::Start(Container::LinkDefinition { .. }, _) => definitions += 1,
+            jotdown::Event::End(Container::LinkDefinition { .. }) => definitions -= 1,
+            _ if 0 < definitions => {}
+            jotdown::Event::Start(container, attrs) => {
+                if container.is_block_container() {
+                    let tight = matches!(
+                        container,
+                        Container::ListItem | Container::TaskListItem { .. }
+                    ) && lists.last() == Some(&true);
+                    bare.push(tight);
+                }
+                // Attributes written before a heading belong to its section
+                let attrs = match container {
+                    Container::Section { .. } => {
+                        section = attrs;
+                        continue;
+                    }
+                    Container::Heading {
+                        has_section: true, ..
+                    } => std::mem::take(&mut section),
+                    _ => attrs,
+                };
+                start(container, &attrs, &mut events, &mut text, &mut lists, &bare);
+            }
+            jotdown::Event::End(container) => {
+                if container.is_block_container() {
+                    bare.pop();
+                }
+                end(container, &mut events, &mut text, &mut lists, &bare);
+            }
+            jotdown::Event::Str(s) => match &mut text {
+                Text::Markup => events.push(Event::Text(s.into())),
+                Text::Literal(literal) => literal.push_str(&s),
+                Text::Html { block: true } => events.push(Event::Html(s.into())),
+                Text::Html { block: false } => events.push(Event::InlineHtml(s.into())),
+                Text::Dropped => {}
+            },
+            jotdown::Event::FootnoteReference(label) => {
+                events.push(Event::FootnoteReference(label.into()));
+            }
+            jotdown::Event::Symbol(symbol) => {
+                events.push(Event::Text(format!(":{symbol}:").into()));
+            }
+            jotdown::Event::LeftSingleQuote => events.push(Event::Text("‘".into())),
+            jotdown::Event::RightSingleQuote => events.push(Event::Text("’".into())),
+            jotdown::Event::LeftDoubleQuote => events.push(Event::Text("“".into())),
+            jotdown::Event::RightDoubleQuote => events.push(Event::Text("”".into())),
+            jotdown::Event::Ellipsis => events.push(Event::Text("…".into())),
+            jotdown::Event::EnDash => events.push(Event::Text("–".into())),
+            jotdown::Event::EmDash => events.push(Event::Text("—".into())),
+            jotdown::Event::NonBreakingSpace => events.push(Event::Text("\u{a0}".into())),
+            jotdown::Event::Softbreak => events.push(Event::SoftBreak),
+            jotdown::Event::Hardbreak => events.push(Event::HardBreak),
+            jotdown::Event::ThematicBreak(_) => events.push(Event::Rule),
+            jotdown::Event::Escape | jotdown::Event::Blankline | jotdown::Event::Attributes(_) => {}
+        }
+    }
+    events
+}
+
+fn start<'a>(
+    container: Container<'a>,
+    attrs: &jotdown::Attributes<'a>,
+    events: &mut Vec<Event<'a>>,
+    text: &mut Text,
+    lists: &mut Vec<bool>,
+    bare: &[bool],
+) {
+    let tag = match container {
+        Container::Document | Container::Section { .. } | Container::LinkDefinition { .. } => {
+            return;
+        }
+        Container::Blockquote => Tag::BlockQuote(None),
+        Container::List { kind, tight } => {
+            lists.push(tight);
+            match kind {
+                jotdown::ListKind::Ordered { start, .. } => Tag::List(Some(start)),
+                jotdown::ListKind::Unordered(_) | jotdown::ListKind::Task(_) => Tag::List(None),
+            }
+        }
+        Container::ListItem => Tag::Item,
+        Container::TaskListItem { checked } => {
+            events.push(Event::Start(Tag::Item));
+            events.push(Event::TaskListMarker(checked));
+            return;
+        }
+        Container::DescriptionList => Tag::DefinitionList,
+        Container::DescriptionTerm => Tag::DefinitionListTitle,
+        Container::DescriptionDetails => Tag::DefinitionListDefinition,
+        Container::Footnote { label } => Tag::FootnoteDefinition(label.into()),
+        Container::Paragraph => {
+            if bare.last() == Some(&true) {
+                return;
+            }
+            Tag::Paragraph
+        }
+        Container::Heading { level, .. } => {
+            let mut classes = Vec::new();
+            let mut others = Vec::new();
+            for (key, value) in attrs.unique_pairs() {
+                let value = value.to_string();
+                match key {
+                    "id" => {}
+                    "class" => classes.extend(
+                        value
+                            .split_whitespace()
+                            .map(|class| CowStr::from(class.to_owned())),
+                    ),
+                    key => others.push((CowStr::from(key.to_owned()), Some(value.into()))),
+                }
+            }
+            Tag::Heading {
+                level: heading_level(usize::from(level)),
+                id: attrs.get_value("id").map(|id| id.to_string().into()),
+                classes,
+                attrs: others,
+            }
+        }
+        Container::CodeBlock { language } => {
+            *text = Text::Literal(String::new());
+            Tag::CodeBlock(CodeBlockKind::Fenced(language.into()))
+        }
+        Container::RawBlock { format } => {
+            *text = raw(&format, true);
+            return;
+        }
+        Container::RawInline { format } => {
+            *text = raw(&format, false);
+            return;
+        }
+        Container::Verbatim | Container::Math { .. } => {
+            *text = Text::Literal(String::new());
+            return;
+        }
+        Container::Link(dest, link_type) => {
+            let (link_type, dest_url) = match link_type {
+                jotdown::LinkType::Span(_) => (LinkType::Inline, dest.into()),
+                jotdown::LinkType::AutoLink => (LinkType::Autolink, dest.into()),
+                jotdown::LinkType::Email => (LinkType::Email, format!("mailto:{dest}").into()),
+            };
+            Tag::Link {
+                link_type,
+                dest_url,
+                title: "".into(),
+                id: "".into(),
+            }
+        }
+        Container::Image(src, _) => Tag::Image {
+            link_type: LinkType::Inline,
+            dest_url: src.into(),
+            title: "".into(),
+            id: "".into(),
+        },
+        Container::Subscript => Tag::Subscript,
+        Container::Superscript => Tag::Superscript,
+        Container::Delete => Tag::Strikethrough,
+        Container::Strong => Tag::Strong,
+        Container::Emphasis => Tag::Emphasis,
+        // Markdown has no equivalent, so these are written as HTML
+        Container::Table => return events.push(Event::Html("<table>\n".into())),
+        Container::TableRow { .. } => return events.push(Event::Html("<tr>\n".into())),
+        Container::TableCell { alignment, head } => {
+            let name = if head { "th" } else { "td" };
+            let style = match alignment {
+                jotdown::Alignment::Unspecified => "",
+                jotdown::Alignment::Left => r#" style="text-align: left;""#,
+                jotdown::Alignment::Center => r#" style="text-align: center;""#,
+                jotdown::Alignment::Right => r#" style="text-align: right;""#,
+            };
+            return events.push(Event::Html(format!("<{name}{style}>").into()));
+        }
+        Container::Caption => return events.push(Event::Html("<caption>".into())),
+        Container::Div { class } => {
+            let tag = open_tag("div", attrs, &class);
+            return events.push(Event::Html(format!("{tag}\n").into()));
+        }
+        Container::Span => {
+            return events.push(Event::InlineHtml(open_tag("span", attrs, "").into()));
+        }
+        Container::Insert => return events.push(Event::InlineHtml("<ins>".into())),
+        Container::Mark => return events.push(Event::InlineHtml("<mark>".into())),
+    };
+    events.push(Event::Start(tag));
+}
+
+fn end<'a>(
+    container: Container<'a>,
+    events: &mut Vec<Event<'a>>,
+    text: &mut Text,
+    lists: &mut Vec<bool>,
+    bare: &[bool],
+) {
+    let tag = match container {
+        Container::Document | Container::Section { .. } | Container::LinkDefinition { .. } => {
+            return;
+        }
+        Container::Blockquote => TagEnd::BlockQuote(None),
+        Container::List { kind, .. } => {
+            lists.pop();
+            TagEnd::List(matches!(kind, jotdown::ListKind::Ordered { .. }))
+        }
+        Container::ListItem | Container::TaskListItem { .. } => TagEnd::Item,
+        Container::DescriptionList => TagEnd::DefinitionList,
+        Container::DescriptionTerm => TagEnd::DefinitionListTitle,
+        Container::DescriptionDetails => TagEnd::DefinitionListDefinition,
+        Container::Footnote { .. } => TagEnd::FootnoteDefinition,
+        Container::Paragraph => {
+            if bare.last() == Some(&true) {
+                return;
+            }
+            TagEnd::Paragraph
+        }
+        Container::Heading { level, .. } => TagEnd::Heading(heading_level(usize::from(level))),
+        Container::CodeBlock { .. } => {
+            if let Text::Literal(code) = std::mem::replace(text, Text::Markup) {
+                events.push(Event::Text(code.into()));
+            }
+            TagEnd::CodeBlock
+        }
+        Container::RawBlock { .. } | Container::RawInline { .. } => {
+            if let Text::Html { block: true } = std::mem::replace(text, Text::Markup) {
+                events.push(Event::Html("\n".into()));
+            }
+            return;
+        }
+        Container::Verbatim => {
+            if let Text::Literal(code) = std::mem::replace(text, Text::Markup) {
+                events.push(Event::Code(code.into()));
+            }
+            return;
+        }
+        Container::Math { display } => {
+            if let Text::Literal(tex) = std::mem::replace(text, Text::Markup) {
+                events.push(if display {
+                    Event::DisplayMath(tex.into())
+                } else {
+                    Event::InlineMath(tex.into())
+                });
+            }
+            return;
+        }
+        Container::Link(..) => TagEnd::Link,
+        Container::Image(..) => TagEnd::Image,
+        Container::Subscript => TagEnd::Subscript,
+        Container::Superscript => TagEnd::Superscript,
+        Container::Delete => TagEnd::Strikethrough,
+        Container::Strong => TagEnd::Strong,
+        Container::Emphasis => TagEnd::Emphasis,
+        Container::Table => return events.push(Event::Html("</table>\n".into())),
+        Container::TableRow { .. } => return events.push(Event::Html("</tr>\n".into())),
+        Container::TableCell { head, .. } => {
+            let html = if head { "</th>\n" } else { "</td>\n" };
+            return events.push(Event::Html(html.into()));
+        }
+        Container::Caption => return events.push(Event::Html("</caption>\n".into())),
+        Container::Div { .. } => return events.push(Event::Html("</div>\n".into())),
+        Container::Span => return events.push(Event::InlineHtml("</span>".into())),
+        Container::Insert => return events.push(Event::InlineHtml("</ins>".into())),
+        Container::Mark => return events.push(Event::InlineHtml("</mark>".into())),
+    };
+    events.push(Event::End(tag));
+}
+
+/// Raw content is only kept when it's HTML
+fn raw(format: &str, block: bool) -> Text {
+    if format == "html" {
+        Text::Html { block }
+    } else {
+        Text::Dropped
+    }
+}
+
+fn open_tag(name: &str, attrs: &jotdown::Attributes<'_>, class: &str) -> String {
+    let mut tag = format!("<{name}");
+    let mut classes = class.to_owned();
+    for (key, value) in attrs.unique_pairs() {
+        let value = value.to_string();
+        if key == "class" {
+            if !classes.is_empty() {
+                classes.push(' ');
+            }
+            classes.push_str(&value);
+        } else {
+            tag.push_str(&format!(r#" {key}="{}""#, escape_attr(&value)));
+        }
+    }
+    if !classes.is_empty() {
+        tag.push_str(&format!(r#" class="{}""#, escape_attr(&classes)));
+    }
+    tag.push('>');
+    tag
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pulldown_cmark as cmark;
+
+    fn render(content: &str) -> String {
+        let mut html = String::new();
+        cmark::html::push_html(&mut html, events(content).into_iter());
+        html
+    }
+
+    #[test]
+    fn blocks() {
+        let html = render(
+            "# A *strong*\nheading\n\n> quoted\n> text\n\n* * *\n\n``` rust\nfn main() {}\n```\n",
+        );
+        assert_eq!(
+            html,
+            "<h1>A <strong>strong</strong>\nheading</h1>\n\
+             <blockquote>\n<p>quoted\ntext</p>\n</blockquote>\n\
+             <hr />\n\
+             <pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let html = render("- one\n- two\n\n  1. nested\n\nafter\n");
+        assert_eq!(
+            html,
+            "<ul>\n<li>one</li>\n<li>two\n<ol>\n<li>nested</li>\n</ol>\n</li>\n</ul>\n<p>after</p>\n"
+        );
+    }
+
+    #[test]
+    fn inline_markup() {
+        let html = render(
+            "_em_ *strong* `a*b` {-del-} {+ins+} {=mark=} x^2^ H~2~O \\*not\\* [link](/a.html) <https://x.y>",
+        );
+        assert_eq!(
+            html,
+            "<p><em>em</em> <strong>strong</strong> <code>a*b</code> <del>del</del> <ins>ins</ins> <mark>mark</mark> x<sup>2</sup> H<sub>2</sub>O *not* <a href=\"/a.html\">link</a> <a href=\"https://x.y\">https://x.y</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let html = render("{#intro .lead}\n# Intro\n\n::: note\n[hi]{.x}\n:::\n");
+        assert_eq!(
+            html,
+            "<h1 id=\"intro\" class=\"lead\">Intro</h1>\n\
+             <div class=\"note\">\n<p><span class=\"x\">hi</span></p>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn math() {
+        let events = events("$`x^2` and $$`y`");
+        assert!(events.contains(&Event::InlineMath("x^2".into())));
+        assert!(events.contains(&Event::DisplayMath("y".into())));
+    }
+
+    #[test]
+    fn raw_html() {
+        let html = render("``` =html\n<video></video>\n```\n\n``` =latex\n\\LaTeX\n```\n");
+        assert_eq!(html, "<video></video>\n");
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::LazyLock;
+
+use liquid::model::KString;
+use liquid::model::Value;
+use pulldown_cmark::CodeBlockKind;
+use pulldown_cmark::Event;
+use pulldown_cmark::Tag;
+use pulldown_cmark::TagEnd;
+use regex::Regex;
+use relative_path::RelativePath;
+
+use crate::cobalt_model;
+use crate::cobalt_model::SourceFormat;
+use crate::error::Result;
+
+#[cfg(feature = "djot")]
+mod djot;
+mod notebook;
+#[cfg(feature = "org")]
+mod org;
+
+/// Turns a document's content, in one source format, into HTML
+pub(crate) trait Renderer: fmt::Debug + Send + Sync {
+    /// Render `content` for the document published at `file_path`
+    fn render(
+        &self,
+        content: &str,
+        front: &cobalt_model::Frontmatter,
+        file_path: &RelativePath,
+    ) -> Result<Rendered>;
+
+    /// Split a file's `content` into its frontmatter and the content to render
+    fn split(&self, content: &str) -> Result<(cobalt_config::Frontmatter, KString)> {
+        Ok(cobalt_config::Document::parse(content)?.into_parts())
+    }
+
+    /// The leading part of `content` to render as the excerpt
+    fn excerpt(&self, content: &str, excerpt_separator: &str) -> String {
+        content
+            .split(excerpt_separator)
+            .next()
+            .unwrap_or(content)
+            .to_owned()
+    }
+
+    /// How to render an `excerpt` given in the frontmatter, when it isn't written in this format
+    fn excerpt_renderer(&self) -> Option<&dyn Renderer> {
+        None
+    }
+}
+
+/// A document's rendered content
+#[derive(Debug)]
+pub(crate) struct Rendered {
+    pub(crate) html: String,
+    /// The table of contents for `page.toc`
+    pub(crate) toc: Value,
+    /// Files to publish next to the document
+    pub(crate) attachments: Vec<Attachment>,
+}
+
+impl Rendered {
+    fn new((html, toc): (String, Value)) -> Self {
+        Self {
+            html,
+            toc,
+            attachments: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Attachment {
+    pub(crate) name: String,
+    pub(crate) data: Vec<u8>,
+}
+
+/// Renderers by the file extensions they handle
+///
+/// Files with any other extension are published as-is, unless their frontmatter sets a
+/// [`SourceFormat`].
+#[derive(Debug, Clone)]
+pub(crate) struct Formats {
+    renderers: HashMap<String, Arc<dyn Renderer>>,
+}
+
+impl Formats {
+    /// The built-in renderers, sharing the site's Markdown settings for code highlighting,
+    /// heading ids and math
+    pub(crate) fn new(markdown: &cobalt_model::Markdown) -> Self {
+        let mut formats = Self {
+            renderers: HashMap::new(),
+        };
+        formats.register(&["md"], Arc::new(markdown.clone()));
+        #[cfg(feature = "djot")]
+        formats.register(&["dj", "djot"], Arc::new(djot::Djot::new(markdown.clone())));
+        #[cfg(feature = "org")]
+        formats.register(&["org"], Arc::new(org::Org::new(markdown.clone())));
+        formats.register(
+            &["ipynb"],
+            Arc::new(notebook::Notebook::new(markdown.clone())),
+        );
+        formats
+    }
+
+    /// Render files ending in any of `extensions` with `renderer`
+    pub(crate) fn register(&mut self, extensions: &[&str], renderer: Arc<dyn Renderer>) {
+        for extension in extensions {
+            self.renderers
+                .insert((*extension).to_owned(), renderer.clone());
+        }
+    }
+
+    /// The renderer for the document at `rel_path`, unless `format` overrides it
+    pub(crate) fn get(
+        &self,
+        format: Option<SourceFormat>,
+        rel_path: &RelativePath,
+    ) -> Arc<dyn Renderer> {
+        let extension = match format {
+            None => source_extension(rel_path),
+            Some(SourceFormat::Raw) => None,
+            Some(SourceFormat::Markdown) => Some("md"),
+        };
+        extension
+            .and_then(|extension| self.renderers.get(extension))
+            .cloned()
+            .unwrap_or_else(|| Arc::new(Raw))
+    }
+}
+
+/// The extension naming the format `rel_path` is written in
+fn source_extension(rel_path: &RelativePath) -> Option<&str> {
+    let extension = rel_path.extension()?;
+    #[cfg(feature = "preview_unstable")]
+    if extension == "liquid" {
+        // `post.md.liquid` is Markdown, templated
+        return RelativePath::new(rel_path.file_stem()?).extension();
+    }
+    Some(extension)
+}
+
+#[derive(Debug)]
+struct Raw;
+
+impl Renderer for Raw {
+    fn render(
+        &self,
+        content: &str,
+        _front: &cobalt_model::Frontmatter,
+        _file_path: &RelativePath,
+    ) -> Result<Rendered> {
+        Ok(Rendered::new((
+            content.to_owned(),
+            Value::Array(Vec::new()),
+        )))
+    }
+}
+
+impl Renderer for cobalt_model::Markdown {
+    fn render(
+        &self,
+        content: &str,
+        front: &cobalt_model::Frontmatter,
+        file_path: &RelativePath,
+    ) -> Result<Rendered> {
+        self.parse_with_toc(content, &front.markdown, file_path.as_str())
+            .map(Rendered::new)
+    }
+
+    /// Keeps reference link definitions from the rest of the document so links in the excerpt
+    /// still resolve
+    fn excerpt(&self, content: &str, excerpt_separator: &str) -> String {
+        static MARKDOWN_REF: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"(?m:^ {0,3}\[[^\]]+\]:.+$)").unwrap());
+
+        let mut trail = String::new();
+
+        if MARKDOWN_REF.is_match(content) {
+            for mat in MARKDOWN_REF.find_iter(content) {
+                trail.push_str(mat.as_str());
+                trail.push('\n');
+            }
+        }
+        trail + content.split(excerpt_separator).next().unwrap_or(content)
+    }
+}
+
+fn push_code_block(lang: &str, code: String, events: &mut Vec<Event<'_>>) {
+    events.push(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(
+        lang.to_owned().into(),
+    ))));
+    events.push(Event::Text(code.into()));
+    events.push(Event::End(TagEnd::CodeBlock));
+}
+
+#[cfg(any(feature = "djot", feature = "org"))]
+fn heading_level(level: usize) -> pulldown_cmark::HeadingLevel {
+    pulldown_cmark::HeadingLevel::try_from(level.clamp(1, 6)).expect("clamped to a valid level")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn formats() -> Formats {
+        let markdown = cobalt_model::MarkdownBuilder {
+            theme: None,
+            extensions: Default::default(),
+            syntax: Arc::new(crate::SyntaxHighlight::new()),
+        }
+        .build();
+        Formats::new(&markdown)
+    }
+
+    fn render(formats: &Formats, format: Option<SourceFormat>, path: &str) -> String {
+        let path = RelativePath::new(path);
+        formats
+            .get(format, path)
+            .render("*hi*", &Default::default(), path)
+            .unwrap()
+            .html
+    }
+
+    #[test]
+    fn renderer_by_extension() {
+        let mut formats = formats();
+        assert_eq!(render(&formats, None, "a.md"), "<p><em>hi</em></p>\n");
+        assert_eq!(render(&formats, None, "a.liquid"), "*hi*");
+        assert_eq!(render(&formats, None, "a.markdown"), "*hi*");
+
+        let markdown = formats.get(None, RelativePath::new("a.md"));
+        formats.register(&["markdown"], markdown);
+        assert_eq!(render(&formats, None, "a.markdown"), "<p><em>hi</em></p>\n");
+    }
+
+    #[test]
+    fn frontmatter_overrides_extension() {
+        let formats = formats();
+        assert_eq!(render(&formats, Some(SourceFormat::Raw), "a.md"), "*hi*");
+        assert_eq!(
+            render(&formats, Some(SourceFormat::Markdown), "a.liquid"),
+            "<p><em>hi</em></p>\n"
+        );
+    }
+}
@@ -0,0 +1,359 @@
+//! Jupyter notebooks (`.ipynb`, nbformat 4)
+//!
+//! Markdown cells go through the site's Markdown and code cells through the syntax highlighter.
+//! Text and HTML outputs are embedded while PNG and SVG outputs are published next to the page.
+//! Frontmatter lives in the notebook's `metadata.cobalt`.
+//! Notebooks are only rendered once `ipynb` is added to `template_extensions`.
+
+use std::sync::LazyLock;
+
+use anyhow::Context as _;
+use base64::Engine as _;
+use liquid::model::KString;
+use pulldown_cmark::Event;
+use regex::Regex;
+use relative_path::RelativePath;
+use serde_json::Map;
+use serde_json::Value as Json;
+
+use super::Attachment;
+use super::Rendered;
+use super::Renderer;
+use super::push_code_block;
+use crate::cobalt_model;
+use crate::error::Result;
+use crate::toc::escape_attr;
+
+/// Output formats, most preferred first
+const MIME_TYPES: &[&str] = &["text/html", "image/svg+xml", "image/png", "text/plain"];
+
+#[derive(Debug)]
+pub(super) struct Notebook {
+    markdown: cobalt_model::Markdown,
+}
+
+impl Notebook {
+    pub(super) fn new(markdown: cobalt_model::Markdown) -> Self {
+        Self { markdown }
+    }
+}
+
+impl Renderer for Notebook {
+    fn split(&self, content: &str) -> Result<(cobalt_config::Frontmatter, KString)> {
+        Ok((frontmatter(content)?, KString::from_ref(content)))
+    }
+
+    fn render(
+        &self,
+        content: &str,
+        front: &cobalt_model::Frontmatter,
+        file_path: &RelativePath,
+    ) -> Result<Rendered> {
+        let notebook: Ipynb = serde_json::from_str(content).context("Failed to parse notebook")?;
+        let language = notebook.metadata.language();
+        let extensions = self.markdown.extensions(&front.markdown);
+        let mut outputs = Outputs {
+            slug: &front.slug,
+            attachments: Vec::new(),
+        };
+
+        let sources: Vec<_> = notebook
+            .cells
+            .iter()
+            .map(|cell| multiline(&cell.source))
+            .collect();
+        let mut events = Vec::new();
+        for (cell, source) in notebook.cells.iter().zip(&sources) {
+            match cell.cell_type.as_str() {
+                "markdown" => {
+                    events.push(Event::Html("<div class=\"cell markdown\">\n".into()));
+                    events.extend(self.markdown.events(source, &extensions));
+                    events.push(Event::Html("</div>\n".into()));
+                }
+                "code" => {
+                    events.push(Event::Html("<div class=\"cell code\">\n".into()));
+                    let mut code = source.clone();
+                    if !code.ends_with('\n') {
+                        code.push('\n');
+                    }
+                    push_code_block(language, code, &mut events);
+                    for output in &cell.outputs {
+                        let html = outputs.render(output)?;
+                        events.push(Event::Html(html.into()));
+                    }
+                    events.push(Event::Html("</div>\n".into()));
+                }
+                _ => {}
+            }
+        }
+
+        let (html, toc) =
+            self.markdown
+                .render_events(events.into_iter(), &extensions, file_path.as_str())?;
+        Ok(Rendered {
+            html,
+            toc,
+            attachments: outputs.attachments,
+        })
+    }
+
+    /// The first Markdown cell, up to the separator
+    fn excerpt(&self, content: &str, excerpt_separator: &str) -> String {
+        let Ok(Json::Object(mut notebook)) = serde_json::from_str::<Json>(content) else {
+            return content.to_owned();
+        };
+        let first = notebook
+            .get("cells")
+            .and_then(Json::as_array)
+            .and_then(|cells| {
+                cells
+                    .iter()
+                    .find(|cell| cell["cell_type"] == "markdown")
+                    .map(|cell| {
+                        let source = multiline(&cell["source"]);
+                        let excerpt = source.split(excerpt_separator).next().unwrap_or_default();
+                        serde_json::json!({ "cell_type": "markdown", "source": excerpt })
+                    })
+            });
+        notebook.insert("cells".into(), Json::Array(first.into_iter().collect()));
+        Json::Object(notebook).to_string()
+    }
+
+    /// A notebook's metadata can only hold text, so it's treated as Markdown
+    fn excerpt_renderer(&self) -> Option<&dyn Renderer> {
+        Some(&self.markdown)
+    }
+}
+
+/// The document's frontmatter, from `metadata.cobalt` with `metadata.title` as a fallback title
+///
+/// Notebooks are never templated.
+fn frontmatter(content: &str) -> Result<cobalt_config::Frontmatter> {
+    #[derive(serde::Deserialize)]
+    struct Header {
+        #[serde(default)]
+        metadata: Metadata,
+    }
+
+    let header: Header = serde_json::from_str(content).context("Failed to parse notebook")?;
+    let mut front: cobalt_config::Frontmatter = match header.metadata.cobalt {
+        Some(front) => serde_json::from_value(front).context("Failed to parse frontmatter")?,
+        None => Default::default(),
+    };
+    if front.title.is_none() {
+        front.title = header.metadata.title.map(Into::into);
+    }
+    front.templated = Some(false);
+    Ok(front)
+}
+
+#[derive(serde::Deserialize)]
+struct Ipynb {
+    #[serde(default)]
+    cells: Vec<Cell>,
+    #[serde(default)]
+    metadata: Metadata,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct Metadata {
+    cobalt: Option<Json>,
+    title: Option<String>,
+    #[serde(default)]
+    kernelspec: Map<String, Json>,
+    #[serde(default)]
+    language_info: Map<String, Json>,
+}
+
+impl Metadata {
+    /// The language code cells are highlighted as
+    fn language(&self) -> &str {
+        self.kernelspec
+            .get("language")
+            .or_else(|| self.language_info.get("name"))
+            .and_then(Json::as_str)
+            .unwrap_or("")
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: Json,
+    #[serde(default)]
+    outputs: Vec<Map<String, Json>>,
+}
+
+/// Notebooks store text either as a string or as a list of lines
+fn multiline(value: &Json) -> String {
+    match value {
+        Json::String(text) => text.clone(),
+        Json::Array(lines) => lines.iter().filter_map(Json::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+struct Outputs<'a> {
+    slug: &'a str,
+    attachments: Vec<Attachment>,
+}
+
+impl Outputs<'_> {
+    fn render(&mut self, output: &Map<String, Json>) -> Result<String> {
+        let html = match output.get("output_type").and_then(Json::as_str) {
+            Some("stream") => {
+                let name = output
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .unwrap_or("stdout");
+                let text = multiline(output.get("text").unwrap_or(&Json::Null));
+                format!(
+                    "<pre class=\"output {}\">{}</pre>\n",
+                    escape_attr(name),
+                    escape_attr(&text)
+                )
+            }
+            Some("error") => {
+                static ANSI_ESCAPE: LazyLock<Regex> =
+                    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap());
+
+                let traceback = output
+                    .get("traceback")
+                    .and_then(Json::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Json::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let traceback = ANSI_ESCAPE.replace_all(&traceback, "");
+                format!(
+                    "<pre class=\"output error\">{}</pre>\n",
+                    escape_attr(&traceback)
+                )
+            }
+            Some("execute_result" | "display_data") => {
+                let data = output.get("data").and_then(Json::as_object);
+                let Some((mime, value)) = MIME_TYPES
+                    .iter()
+                    .find_map(|mime| Some((*mime, data?.get(*mime)?)))
+                else {
+                    return Ok(String::new());
+                };
+                let value = multiline(value);
+                match mime {
+                    "text/html" => format!("<div class=\"output html\">\n{value}\n</div>\n"),
+                    "image/svg+xml" => self.image("svg", value.into_bytes()),
+                    "image/png" => {
+                        let encoded: String =
+                            value.chars().filter(|c| !c.is_whitespace()).collect();
+                        let data = base64::engine::general_purpose::STANDARD
+                            .decode(encoded)
+                            .context("Failed to decode PNG output")?;
+                        self.image("png", data)
+                    }
+                    _ => format!("<pre class=\"output\">{}</pre>\n", escape_attr(&value)),
+                }
+            }
+            _ => String::new(),
+        };
+        Ok(html)
+    }
+
+    /// Publish an image next to the page, returning the HTML to show it
+    fn image(&mut self, extension: &str, data: Vec<u8>) -> String {
+        let name = format!("{}-{}.{extension}", self.slug, self.attachments.len() + 1);
+        // Written next to the page, so the link holds wherever the site is served from
+        let html = format!(
+            "<img class=\"output\" src=\"{}\" alt=\"\" />\n",
+            escape_attr(&name)
+        );
+        self.attachments.push(Attachment { name, data });
+        html
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+      "metadata": {
+        "kernelspec": { "language": "python" },
+        "cobalt": { "title": "Analysis", "tags": ["data"] }
+      },
+      "nbformat": 4,
+      "cells": [
+        { "cell_type": "markdown", "source": ["# Results\n", "\n", "Summary first.\n", "\n", "Details later."] },
+        {
+          "cell_type": "code",
+          "source": "print(1 < 2)",
+          "outputs": [
+            { "output_type": "stream", "name": "stdout", "text": ["True\n"] },
+            { "output_type": "execute_result", "data": { "text/plain": ["<Axes>"], "image/png": "iVBORw0KGgo=" } },
+            { "output_type": "display_data", "data": { "text/html": "<table></table>" } },
+            { "output_type": "error", "traceback": ["\u001b[0;31mValueError\u001b[0m: bad"] }
+          ]
+        },
+        { "cell_type": "raw", "source": "ignored" }
+      ]
+    }"##;
+
+    fn notebook() -> Notebook {
+        let markdown = cobalt_model::MarkdownBuilder {
+            theme: None,
+            extensions: Default::default(),
+            syntax: std::sync::Arc::new(crate::SyntaxHighlight::new()),
+        }
+        .build();
+        Notebook::new(markdown)
+    }
+
+    fn front() -> cobalt_model::Frontmatter {
+        cobalt_model::Frontmatter {
+            slug: "analysis".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn frontmatter_from_metadata() {
+        let front = frontmatter(NOTEBOOK).unwrap();
+        assert_eq!(front.title.as_deref(), Some("Analysis"));
+        assert_eq!(front.tags, Some(vec!["data".into()]));
+        assert_eq!(front.templated, Some(false));
+    }
+
+    #[test]
+    fn render_cells_and_outputs() {
+        let rendered = notebook()
+            .render(NOTEBOOK, &front(), RelativePath::new("posts/analysis.html"))
+            .unwrap();
+        let html = rendered.html;
+        assert!(html.starts_with("<div class=\"cell markdown\">\n<h1>Results</h1>"));
+        assert!(
+            html.contains("<pre class=\"output stdout\">True\n</pre>"),
+            "{html}"
+        );
+        assert!(html.contains("<img class=\"output\" src=\"analysis-1.png\" alt=\"\" />"));
+        assert!(html.contains("<div class=\"output html\">\n<table></table>\n</div>"));
+        assert!(html.contains("<pre class=\"output error\">ValueError: bad</pre>"));
+        assert!(!html.contains("ignored"));
+        assert_eq!(rendered.attachments.len(), 1);
+        assert_eq!(rendered.attachments[0].name, "analysis-1.png");
+        assert_eq!(rendered.attachments[0].data, b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn excerpt_is_first_markdown_paragraphs() {
+        let notebook = notebook();
+        let excerpt = notebook.excerpt(NOTEBOOK, "\n\nDetails");
+        let rendered = notebook
+            .render(&excerpt, &front(), RelativePath::new("analysis.html"))
+            .unwrap();
+        assert_eq!(
+            rendered.html,
+            "<div class=\"cell markdown\">\n<h1>Results</h1>\n<p>Summary first.</p>\n</div>\n"
+        );
+    }
+}
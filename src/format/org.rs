@@ -0,0 +1,270 @@
+//! [Org-mode](https://orgmode.org), parsed by `orgize`
+//!
+//! The parsed document is translated into Markdown's events so code blocks and headings render
+//! like they do in Markdown.  A headline's `CUSTOM_ID` property becomes its id.  `orgize` doesn't
+//! parse LaTeX fragments, so math is left as text.
+
+use orgize::Element;
+use pulldown_cmark::Event;
+use pulldown_cmark::LinkType;
+use pulldown_cmark::Tag;
+use pulldown_cmark::TagEnd;
+use relative_path::RelativePath;
+
+use super::Rendered;
+use super::Renderer;
+use super::heading_level;
+use super::push_code_block;
+use crate::cobalt_model;
+use crate::error::Result;
+
+/// Links to these are shown as images, when they have no description
+const IMAGE_EXTENSIONS: &[&str] = &[".png", ".jpg", ".jpeg", ".gif", ".svg", ".webp"];
+
+#[derive(Debug)]
+pub(super) struct Org {
+    markdown: cobalt_model::Markdown,
+}
+
+impl Org {
+    pub(super) fn new(markdown: cobalt_model::Markdown) -> Self {
+        Self { markdown }
+    }
+}
+
+impl Renderer for Org {
+    fn render(
+        &self,
+        content: &str,
+        front: &cobalt_model::Frontmatter,
+        file_path: &RelativePath,
+    ) -> Result<Rendered> {
+        let extensions = self.markdown.extensions(&front.markdown);
+        self.markdown
+            .render_events(events(content).into_iter(), &extensions, file_path.as_str())
+            .map(Rendered::new)
+    }
+}
+
+fn events(content: &str) -> Vec<Event<'static>> {
+    let org = orgize::Org::parse(content);
+    let mut events = Vec::new();
+    // Whether each open container is a list item, whose paragraphs are left out like Org's
+    // HTML export does
+    let mut items: Vec<bool> = Vec::new();
+    for event in org.iter() {
+        match event {
+            orgize::Event::Start(element) => {
+                let in_item = items.last() == Some(&true);
+                if element.is_container() {
+                    items.push(matches!(element, Element::ListItem(_)));
+                }
+                start(element, &mut events, in_item);
+            }
+            orgize::Event::End(element) => {
+                if element.is_container() {
+                    items.pop();
+                    end(element, &mut events, items.last() == Some(&true));
+                }
+            }
+        }
+    }
+    events
+}
+
+fn start(element: &Element<'_>, events: &mut Vec<Event<'static>>, in_item: bool) {
+    let tag = match element {
+        Element::QuoteBlock(_) => Tag::BlockQuote(None),
+        Element::List(list) => Tag::List(list.ordered.then_some(1)),
+        Element::ListItem(_) => Tag::Item,
+        Element::Paragraph { .. } if in_item => return,
+        Element::Paragraph { .. } => Tag::Paragraph,
+        Element::Title(title) => Tag::Heading {
+            level: heading_level(title.level),
+            id: title
+                .properties
+                .pairs
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("CUSTOM_ID"))
+                .map(|(_, id)| id.to_string().into()),
+            classes: Vec::new(),
+            attrs: Vec::new(),
+        },
+        Element::Bold => Tag::Strong,
+        Element::Italic => Tag::Emphasis,
+        Element::Strike => Tag::Strikethrough,
+        Element::Underline => return events.push(Event::InlineHtml("<u>".into())),
+        Element::Table(orgize::elements::Table::Org { .. }) => {
+            return events.push(Event::Html("<table>\n".into()));
+        }
+        Element::TableRow(
+            orgize::elements::TableRow::Header | orgize::elements::TableRow::Body,
+        ) => return events.push(Event::Html("<tr>\n".into())),
+        Element::TableCell(orgize::elements::TableCell::Header) => {
+            return events.push(Event::Html("<th>".into()));
+        }
+        Element::TableCell(orgize::elements::TableCell::Body) => {
+            return events.push(Event::Html("<td>".into()));
+        }
+        Element::Text { value } => return events.push(Event::Text(value.to_string().into())),
+        Element::Verbatim { value } | Element::Code { value } => {
+            return events.push(Event::Code(value.to_string().into()));
+        }
+        Element::InlineSrc(src) => return events.push(Event::Code(src.body.to_string().into())),
+        Element::Link(link) => return push_link(link, events),
+        Element::Snippet(snippet) if snippet.name.eq_ignore_ascii_case("html") => {
+            return events.push(Event::InlineHtml(snippet.value.to_string().into()));
+        }
+        Element::SourceBlock(block) => {
+            return push_code_block(&block.language, block.contents.to_string(), events);
+        }
+        Element::ExampleBlock(block) => {
+            return push_code_block("", block.contents.to_string(), events);
+        }
+        Element::FixedWidth(fixed) => {
+            // `orgize` keeps the colon starting each line
+            let code: String = fixed
+                .value
+                .lines()
+                .map(|line| {
+                    let line = line.trim_start();
+                    let line = line.strip_prefix(':').unwrap_or(line);
+                    let line = line.strip_prefix(' ').unwrap_or(line);
+                    format!("{line}\n")
+                })
+                .collect();
+            return push_code_block("", code, events);
+        }
+        Element::Table(orgize::elements::Table::TableEl { value, .. }) => {
+            return push_code_block("", value.to_string(), events);
+        }
+        Element::ExportBlock(block) if block.data.eq_ignore_ascii_case("html") => {
+            return events.push(Event::Html(block.contents.to_string().into()));
+        }
+        Element::Rule(_) => return events.push(Event::Rule),
+        // Structure that only matters to Org, metadata and anything not meant for export
+        _ => return,
+    };
+    events.push(Event::Start(tag));
+}
+
+fn end(element: &Element<'_>, events: &mut Vec<Event<'static>>, in_item: bool) {
+    let tag = match element {
+        Element::QuoteBlock(_) => TagEnd::BlockQuote(None),
+        Element::List(list) => TagEnd::List(list.ordered),
+        Element::ListItem(_) => TagEnd::Item,
+        Element::Paragraph { .. } if in_item => return,
+        Element::Paragraph { .. } => TagEnd::Paragraph,
+        Element::Title(title) => TagEnd::Heading(heading_level(title.level)),
+        Element::Bold => TagEnd::Strong,
+        Element::Italic => TagEnd::Emphasis,
+        Element::Strike => TagEnd::Strikethrough,
+        Element::Underline => return events.push(Event::InlineHtml("</u>".into())),
+        Element::Table(_) => return events.push(Event::Html("</table>\n".into())),
+        Element::TableRow(_) => return events.push(Event::Html("</tr>\n".into())),
+        Element::TableCell(orgize::elements::TableCell::Header) => {
+            return events.push(Event::Html("</th>\n".into()));
+        }
+        Element::TableCell(orgize::elements::TableCell::Body) => {
+            return events.push(Event::Html("</td>\n".into()));
+        }
+        _ => return,
+    };
+    events.push(Event::End(tag));
+}
+
+fn push_link(link: &orgize::elements::Link<'_>, events: &mut Vec<Event<'static>>) {
+    let path = link.path.to_string();
+    let is_image = IMAGE_EXTENSIONS
+        .iter()
+        .any(|ext| path.to_ascii_lowercase().ends_with(ext));
+    match &link.desc {
+        None if is_image => {
+            events.push(Event::Start(Tag::Image {
+                link_type: LinkType::Inline,
+                dest_url: path.into(),
+                title: "".into(),
+                id: "".into(),
+            }));
+            events.push(Event::End(TagEnd::Image));
+        }
+        desc => {
+            let text = desc.as_deref().unwrap_or(&path).to_owned();
+            events.push(Event::Start(Tag::Link {
+                link_type: LinkType::Inline,
+                dest_url: path.into(),
+                title: "".into(),
+                id: "".into(),
+            }));
+            events.push(Event::Text(text.into()));
+            events.push(Event::End(TagEnd::Link));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pulldown_cmark as cmark;
+
+    fn render(content: &str) -> String {
+        let mut html = String::new();
+        cmark::html::push_html(&mut html, events(content).into_iter());
+        html
+    }
+
+    #[test]
+    fn blocks() {
+        let html = render(
+            "#+TITLE: Ignored\n* Intro :draft:\nSome text\nwrapped.\n# comment\n-----\n#+BEGIN_SRC rust\nfn main() {}\n#+END_SRC\n: fixed\n",
+        );
+        assert_eq!(
+            html,
+            "<h1>Intro</h1>\n<p>Some text\nwrapped.</p>\n<hr />\n\
+             <pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n\
+             <pre><code>fixed\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn custom_id() {
+        let html = render("* Intro\n:PROPERTIES:\n:CUSTOM_ID: start\n:END:\n");
+        assert_eq!(html, "<h1 id=\"start\">Intro</h1>\n");
+    }
+
+    #[test]
+    fn lists() {
+        let html = render("- one\n- two\n  1. nested\n\nafter\n");
+        assert_eq!(
+            html,
+            "<ul>\n<li>one</li>\n<li>two\n<ol>\n<li>nested</li>\n</ol>\n</li>\n</ul>\n<p>after</p>\n"
+        );
+    }
+
+    #[test]
+    fn quote_block() {
+        let html = render("#+begin_quote\nWise words.\n#+end_quote\n");
+        assert_eq!(html, "<blockquote>\n<p>Wise words.</p>\n</blockquote>\n");
+    }
+
+    #[test]
+    fn inline_markup() {
+        let html = render(
+            "*bold* /italic/ _under_ +gone+ =verb*atim= ~code~ snake_case_name [[https://x.y][a site]] [[/img.png]]",
+        );
+        assert_eq!(
+            html,
+            "<p><strong>bold</strong> <em>italic</em> <u>under</u> <del>gone</del> <code>verb*atim</code> <code>code</code> snake_case_name <a href=\"https://x.y\">a site</a> <img src=\"/img.png\" alt=\"\" /></p>\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let html = render("| a | b |\n|---+---|\n| 1 | 2 |\n");
+        assert_eq!(
+            html,
+            "<table>\n<tr>\n<th>a</th>\n<th>b</th>\n</tr>\n<tr>\n<td>1</td>\n<td>2</td>\n</tr>\n</table>\n"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+
+use liquid::Object;
+use liquid::ValueView;
+use liquid::model::DisplayCow;
+use liquid::model::KStringCow;
+use liquid::model::ObjectView;
+use liquid::model::State;
+use liquid::model::Value;
+
+/// The top-level variables for rendering a document
+///
+/// Site-wide data and collections are built once per build and borrowed by every document
+/// rather than being copied into a fresh `Object` for each one.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Globals<'a> {
+    pub(crate) site: &'a Object,
+    pub(crate) collections: Option<&'a Object>,
+    pub(crate) paginator: Option<&'a Object>,
+    pub(crate) page: &'a Object,
+    pub(crate) layout: Option<&'a Object>,
+}
+
+impl<'a> Globals<'a> {
+    pub(crate) fn new(site: &'a Object, page: &'a Object) -> Self {
+        Self {
+            site,
+            collections: None,
+            paginator: None,
+            page,
+            layout: None,
+        }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (&'static str, &'a Object)> {
+        [
+            ("site", Some(self.site)),
+            ("collections", self.collections),
+            ("paginator", self.paginator),
+            ("page", Some(self.page)),
+            ("layout", self.layout),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key, value)))
+    }
+}
+
+impl ValueView for Globals<'_> {
+    fn as_debug(&self) -> &dyn fmt::Debug {
+        self
+    }
+
+    fn render(&self) -> DisplayCow<'_> {
+        DisplayCow::Owned(Box::new(self.to_value().render().to_string()))
+    }
+
+    fn source(&self) -> DisplayCow<'_> {
+        DisplayCow::Owned(Box::new(self.to_value().source().to_string()))
+    }
+
+    fn type_name(&self) -> &'static str {
+        "object"
+    }
+
+    fn query_state(&self, state: State) -> bool {
+        match state {
+            State::Truthy => true,
+            State::DefaultValue | State::Empty | State::Blank => false,
+        }
+    }
+
+    fn to_kstr(&self) -> KStringCow<'_> {
+        KStringCow::from_string(self.render().to_string())
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Object(
+            self.entries()
+                .map(|(key, value)| (key.into(), Value::Object(value.clone())))
+                .collect(),
+        )
+    }
+
+    fn as_object(&self) -> Option<&dyn ObjectView> {
+        Some(self)
+    }
+}
+
+impl ObjectView for Globals<'_> {
+    fn as_value(&self) -> &dyn ValueView {
+        self
+    }
+
+    fn size(&self) -> i64 {
+        self.entries().count() as i64
+    }
+
+    fn keys<'k>(&'k self) -> Box<dyn Iterator<Item = KStringCow<'k>> + 'k> {
+        Box::new(self.entries().map(|(key, _)| KStringCow::from_static(key)))
+    }
+
+    fn values<'k>(&'k self) -> Box<dyn Iterator<Item = &'k dyn ValueView> + 'k> {
+        Box::new(self.entries().map(|(_, value)| value.as_value()))
+    }
+
+    fn iter<'k>(&'k self) -> Box<dyn Iterator<Item = (KStringCow<'k>, &'k dyn ValueView)> + 'k> {
+        Box::new(
+            self.entries()
+                .map(|(key, value)| (KStringCow::from_static(key), value.as_value())),
+        )
+    }
+
+    fn contains_key(&self, index: &str) -> bool {
+        self.get(index).is_some()
+    }
+
+    fn get<'s>(&'s self, index: &str) -> Option<&'s dyn ValueView> {
+        self.entries()
+            .find(|(key, _)| *key == index)
+            .map(|(_, value)| value.as_value())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_borrowed_globals() {
+        let site = liquid::object!({ "title": "Site" });
+        let page = liquid::object!({ "title": "Page" });
+        let collections = liquid::object!({ "posts": { "pages": [ { "title": "Post" } ] } });
+        let globals = Globals {
+            collections: Some(&collections),
+            ..Globals::new(&site, &page)
+        };
+
+        let template = liquid::ParserBuilder::with_stdlib()
+            .build()
+            .unwrap()
+            .parse("{{ site.title }} {{ page.title }} {{ collections.posts.pages[0].title }}")
+            .unwrap();
+        assert_eq!(template.render(&globals).unwrap(), "Site Page Post");
+    }
+
+    #[test]
+    fn omits_unset_globals() {
+        let site = Object::new();
+        let page = Object::new();
+        let globals = Globals::new(&site, &page);
+
+        assert!(!globals.contains_key("paginator"));
+        assert_eq!(ObjectView::size(&globals), 2);
+    }
+}
@@ -0,0 +1,211 @@
+//! Hashes for Subresource Integrity and `Content-Security-Policy` sources
+
+use std::sync::LazyLock;
+
+use base64::Engine as _;
+use regex::Regex;
+use sha2::Digest as _;
+
+use crate::error::Result;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Algorithm {
+    Sha256,
+    #[default]
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    pub(crate) fn parse(name: &str) -> Result<Self> {
+        match name {
+            "sha256" => Ok(Self::Sha256),
+            "sha384" => Ok(Self::Sha384),
+            "sha512" => Ok(Self::Sha512),
+            _ => anyhow::bail!(
+                "Unsupported hash algorithm `{name}`, expected `sha256`, `sha384` or `sha512`"
+            ),
+        }
+    }
+
+    /// Hash `data` as an integrity value, e.g. `sha384-…`
+    pub(crate) fn integrity(self, data: &[u8]) -> String {
+        let (name, digest) = match self {
+            Self::Sha256 => ("sha256", sha2::Sha256::digest(data).to_vec()),
+            Self::Sha384 => ("sha384", sha2::Sha384::digest(data).to_vec()),
+            Self::Sha512 => ("sha512", sha2::Sha512::digest(data).to_vec()),
+        };
+        let digest = base64::engine::general_purpose::STANDARD.encode(digest);
+        format!("{name}-{digest}")
+    }
+}
+
+/// Stand-ins for `page.csp` while a page renders, replaced by its hashes once they're known
+const SCRIPT_SRC_PLACEHOLDER: &str = "cobalt-csp-script-src";
+const STYLE_SRC_PLACEHOLDER: &str = "cobalt-csp-style-src";
+
+/// Hashes of inline `<script>` and `<style>` blocks, for a page's `page.csp` or across the site
+/// for `csp.hashes_file`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct InlineHashes {
+    script_src: Vec<String>,
+    style_src: Vec<String>,
+}
+
+impl InlineHashes {
+    /// `page.csp` for rendering, before the page's hashes are known
+    pub(crate) fn placeholders() -> liquid::model::Value {
+        liquid::model::Value::Object(liquid::object!({
+            "script_src": SCRIPT_SRC_PLACEHOLDER,
+            "style_src": STYLE_SRC_PLACEHOLDER,
+        }))
+    }
+
+    /// The hashes of the blocks in a rendered page
+    pub(crate) fn from_html(html: &str) -> Self {
+        let mut hashes = Self::default();
+        hashes.add(html);
+        hashes
+    }
+
+    /// Whether `html` still carries [`InlineHashes::placeholders`] inside an inline block, where
+    /// replacing them would change the block's own hash
+    pub(crate) fn hashes_placeholders(html: &str) -> bool {
+        inline_blocks(html).any(|block| {
+            block.contains(SCRIPT_SRC_PLACEHOLDER) || block.contains(STYLE_SRC_PLACEHOLDER)
+        })
+    }
+
+    /// Replace [`InlineHashes::placeholders`] in `html` with these hashes
+    pub(crate) fn fill_placeholders(&self, html: &str) -> String {
+        html.replace(SCRIPT_SRC_PLACEHOLDER, &self.script_src.join(" "))
+            .replace(STYLE_SRC_PLACEHOLDER, &self.style_src.join(" "))
+    }
+
+    /// Add hashes from `other` not seen yet
+    pub(crate) fn extend(&mut self, other: &Self) {
+        add_sources(&mut self.script_src, other.script_src.iter().cloned());
+        add_sources(&mut self.style_src, other.style_src.iter().cloned());
+    }
+
+    /// `page.csp`, with each directive's sources space separated
+    pub(crate) fn to_value(&self) -> liquid::model::Value {
+        liquid::model::Value::Object(liquid::object!({
+            "script_src": self.script_src.join(" "),
+            "style_src": self.style_src.join(" "),
+        }))
+    }
+
+    /// Add the hashes of the blocks in `html` not seen yet
+    pub(crate) fn add(&mut self, html: &str) {
+        add_sources(&mut self.script_src, scripts(html).map(hash_source));
+        add_sources(&mut self.style_src, styles(html).map(hash_source));
+    }
+
+    /// The sources for each directive as JSON, e.g. `{"script_src": "'sha256-…' 'sha256-…'"}`
+    pub(crate) fn to_json(&self) -> String {
+        let json = serde_json::json!({
+            "script_src": self.script_src.join(" "),
+            "style_src": self.style_src.join(" "),
+        });
+        serde_json::to_string_pretty(&json).expect("always serializable")
+    }
+}
+
+static SCRIPT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<script\b([^>]*)>(.*?)</script\s*>").unwrap());
+static STYLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<style\b[^>]*>(.*?)</style\s*>").unwrap());
+static SRC: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\ssrc\s*=").unwrap());
+
+/// The contents of inline `<script>` blocks, skipping empty ones and those loading a `src`
+fn scripts(html: &str) -> impl Iterator<Item = &str> {
+    SCRIPT
+        .captures_iter(html)
+        .filter(|block| !SRC.is_match(&block[1]))
+        .map(|block| block.get(2).expect("always captured").as_str())
+        .filter(|block| !block.is_empty())
+}
+
+/// The contents of non-empty `<style>` blocks
+fn styles(html: &str) -> impl Iterator<Item = &str> {
+    STYLE
+        .captures_iter(html)
+        .map(|block| block.get(1).expect("always captured").as_str())
+        .filter(|block| !block.is_empty())
+}
+
+fn inline_blocks(html: &str) -> impl Iterator<Item = &str> {
+    scripts(html).chain(styles(html))
+}
+
+fn hash_source(block: &str) -> String {
+    format!("'{}'", Algorithm::Sha256.integrity(block.as_bytes()))
+}
+
+fn add_sources(sources: &mut Vec<String>, new: impl Iterator<Item = String>) {
+    for source in new {
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn integrity_values() {
+        assert_eq!(
+            Algorithm::Sha256.integrity(b"alert('Hello, world.');"),
+            "sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng="
+        );
+        assert!(Algorithm::default().integrity(b"").starts_with("sha384-"));
+        assert!(Algorithm::parse("sha512").is_ok());
+        assert!(Algorithm::parse("md5").is_err());
+    }
+
+    #[test]
+    fn hash_inline_blocks() {
+        let mut hashes = InlineHashes::default();
+        hashes.add(
+            "<head>\
+            <script src=\"/js/site.js\"></script>\
+            <script>alert('Hello, world.');</script>\
+            <SCRIPT type=\"module\">alert('Hello, world.');</SCRIPT>\
+            <style media=\"print\">a{color:red}</style>\
+            </head>",
+        );
+        hashes.add("<p>No scripts</p><style>a{color:red}</style><style>b{color:blue}</style>");
+        assert_eq!(
+            hashes.script_src,
+            ["'sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='"]
+        );
+        assert_eq!(
+            hashes.style_src,
+            [
+                format!("'{}'", Algorithm::Sha256.integrity(b"a{color:red}")),
+                format!("'{}'", Algorithm::Sha256.integrity(b"b{color:blue}")),
+            ]
+        );
+    }
+
+    #[test]
+    fn fill_page_placeholders() {
+        let html = format!(
+            "<meta content=\"script-src {SCRIPT_SRC_PLACEHOLDER}; style-src {STYLE_SRC_PLACEHOLDER}\">\
+            <script>alert('Hello, world.');</script>"
+        );
+        assert!(!InlineHashes::hashes_placeholders(&html));
+        let hashes = InlineHashes::from_html(&html);
+        assert_eq!(
+            hashes.fill_placeholders(&html),
+            "<meta content=\"script-src 'sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='; style-src \">\
+            <script>alert('Hello, world.');</script>"
+        );
+        assert!(InlineHashes::hashes_placeholders(&format!(
+            "<script>const csp = \"{SCRIPT_SRC_PLACEHOLDER}\";</script>"
+        )));
+    }
+}
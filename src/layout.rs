@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path;
+
+use anyhow::Context as _;
+use log::debug;
+use log::trace;
+use log::warn;
+
+use crate::cobalt_model;
+use crate::cobalt_model::files;
+use crate::error::Result;
+
+/// Compiled templates from `_layouts`
+///
+/// Each layout is compiled once and shared by every document that uses it.  Keeping the cache
+/// between builds (like `cobalt serve` does) limits recompilation to the layouts that changed.
+/// Changes to anything compiled into templates recompile all layouts: the Liquid settings,
+/// includes, shortcodes, syntaxes and themes.
+#[derive(Debug, Default)]
+pub struct LayoutCache {
+    fingerprint: Option<u64>,
+    layouts: HashMap<String, CompiledLayout>,
+}
+
+impl LayoutCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Reload `_layouts`, compiling only what is new or changed since the last update
+    pub(crate) fn update(
+        &mut self,
+        layouts_path: &path::Path,
+        parser: &cobalt_model::Liquid,
+    ) -> Result<()> {
+        if self.fingerprint != Some(parser.fingerprint()) {
+            if self.fingerprint.is_some() {
+                debug!("Template settings changed, recompiling all layouts");
+            }
+            self.layouts.clear();
+            self.fingerprint = Some(parser.fingerprint());
+        }
+
+        let files = find_layouts(layouts_path)?;
+        let mut layouts = HashMap::with_capacity(self.layouts.len());
+        for file_path in files.files() {
+            let rel_src = file_path
+                .strip_prefix(files.root())
+                .expect("file was found under the root");
+            let layout = load_layout(&file_path, rel_src).and_then(|(name, source)| {
+                let layout = match self.layouts.remove(&name) {
+                    Some(layout) if layout.source == source => layout,
+                    _ => {
+                        trace!("Compiling layout `{name}`");
+                        CompiledLayout::compile(source, parser).with_context(|| {
+                            anyhow::format_err!("Failed to parse layout {}", rel_src.display())
+                        })?
+                    }
+                };
+                Ok((name, layout))
+            });
+            match layout {
+                Ok((name, layout)) => {
+                    layouts.insert(name, layout);
+                }
+                Err(error) => warn!("{error}"),
+            }
+        }
+        self.layouts = layouts;
+
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&CompiledLayout> {
+        self.layouts.get(name)
+    }
+}
+
+/// A layout's frontmatter alongside its compiled template
+pub(crate) struct CompiledLayout {
+    source: String,
+    front: liquid::Object,
+    parent: Option<String>,
+    template: std::result::Result<liquid::Template, String>,
+}
+
+impl CompiledLayout {
+    fn compile(source: String, parser: &cobalt_model::Liquid) -> Result<Self> {
+        let layout = cobalt_config::Layout::parse(&source)?;
+        let parent = layout.layout().map(ToOwned::to_owned);
+        let (front, content) = layout.into_parts();
+        // Report template errors when a document uses the layout, like we would for the document
+        let template = parser.parse(&content).map_err(|e| format!("{e:#}"));
+        Ok(Self {
+            source,
+            front,
+            parent,
+            template,
+        })
+    }
+
+    /// The layout this layout is rendered into
+    pub(crate) fn layout(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    pub(crate) fn front(&self) -> &liquid::Object {
+        &self.front
+    }
+
+    pub(crate) fn template(&self) -> Result<&liquid::Template> {
+        self.template
+            .as_ref()
+            .map_err(|e| anyhow::format_err!("{}", e))
+    }
+}
+
+impl fmt::Debug for CompiledLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledLayout")
+            .field("front", &self.front)
+            .field("parent", &self.parent)
+            .finish_non_exhaustive()
+    }
+}
+
+fn find_layouts(layouts: &path::Path) -> Result<files::Files> {
+    let mut files = files::FilesBuilder::new(layouts)?;
+    files.ignore_hidden(false)?;
+    files.build()
+}
+
+fn load_layout(file_path: &path::Path, rel_src: &path::Path) -> Result<(String, String)> {
+    let layout_data = files::read_file(file_path)
+        .with_context(|| anyhow::format_err!("Failed to load layout {}", rel_src.display()))?;
+
+    let name = rel_src
+        .to_str()
+        .ok_or_else(|| {
+            anyhow::format_err!("File name not valid liquid path: {}", rel_src.display())
+        })?
+        .to_owned();
+
+    Ok((name, layout_data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn liquid(root: &path::Path) -> cobalt_model::Liquid {
+        builder(root).build().unwrap()
+    }
+
+    fn builder(root: &path::Path) -> cobalt_model::LiquidBuilder {
+        let syntax = std::sync::Arc::new(crate::SyntaxHighlight::new());
+        cobalt_model::LiquidBuilder {
+            includes_path: root.join("_includes"),
+            shortcodes_path: root.join("_shortcodes"),
+            source_path: root.to_owned(),
+            destination_path: root.join("_site"),
+            bundles: Default::default(),
+            theme: None,
+            syntax: syntax.clone(),
+            markdown: cobalt_model::MarkdownBuilder {
+                syntax,
+                theme: None,
+                extensions: Default::default(),
+            },
+        }
+    }
+
+    fn render(cache: &LayoutCache, name: &str) -> String {
+        cache
+            .get(name)
+            .unwrap()
+            .template()
+            .unwrap()
+            .render(&liquid::Object::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn update_picks_up_changes() {
+        let root = tempfile::tempdir().unwrap();
+        let layouts_path = root.path().join("_layouts");
+        std::fs::create_dir_all(&layouts_path).unwrap();
+        std::fs::create_dir_all(root.path().join("_includes")).unwrap();
+        std::fs::write(root.path().join("_includes/x.liquid"), "one").unwrap();
+        std::fs::write(
+            layouts_path.join("a.liquid"),
+            "---\nlayout: b.liquid\n---\n{% include 'x.liquid' %}",
+        )
+        .unwrap();
+        std::fs::write(layouts_path.join("b.liquid"), "b").unwrap();
+
+        let mut cache = LayoutCache::new();
+        cache.update(&layouts_path, &liquid(root.path())).unwrap();
+        assert_eq!(cache.get("a.liquid").unwrap().layout(), Some("b.liquid"));
+        assert_eq!(render(&cache, "a.liquid"), "one");
+
+        std::fs::write(root.path().join("_includes/x.liquid"), "two").unwrap();
+        std::fs::write(layouts_path.join("a.liquid"), "{% include 'x.liquid' %}").unwrap();
+        std::fs::remove_file(layouts_path.join("b.liquid")).unwrap();
+        cache.update(&layouts_path, &liquid(root.path())).unwrap();
+        assert_eq!(cache.get("a.liquid").unwrap().layout(), None);
+        assert_eq!(render(&cache, "a.liquid"), "two");
+        assert!(cache.get("b.liquid").is_none());
+    }
+
+    #[test]
+    fn fingerprint_covers_settings() {
+        let root = tempfile::tempdir().unwrap();
+        let fingerprint = liquid(root.path()).fingerprint();
+        assert_eq!(liquid(root.path()).fingerprint(), fingerprint);
+
+        let mut changed = builder(root.path());
+        changed.markdown.extensions.heading_ids = Some(true);
+        assert_ne!(changed.build().unwrap().fingerprint(), fingerprint);
+    }
+}
@@ -3,18 +3,29 @@
 #![warn(clippy::print_stdout)]
 
 pub use crate::cobalt::build;
+pub use crate::cobalt::build_with_layouts;
 pub use crate::cobalt::classify_path;
 pub use crate::cobalt_model::Config;
 pub use crate::error::Error;
+pub use crate::layout::LayoutCache;
 
 pub mod cobalt_model;
 pub mod error;
 
 mod cobalt;
+mod compress;
 mod document;
+mod filters;
+mod format;
+mod globals;
+mod integrity;
+mod layout;
+mod math;
 
 mod pagination;
+mod shortcode;
 mod syntax_highlight;
+mod toc;
 
 pub use crate::syntax_highlight::SyntaxHighlight;
 
@@ -0,0 +1,145 @@
+use pulldown_cmark::Event;
+
+/// Converts `$...$` and `$$...$$` math to `MathML` at build time
+///
+/// TeX that can't be converted is left as-is (in a `math` span) with a warning naming `source`.
+pub(crate) struct MathToMathml<'s, I> {
+    events: I,
+    source: &'s str,
+}
+
+impl<'s, I> MathToMathml<'s, I> {
+    pub(crate) fn new(events: I, source: &'s str) -> Self {
+        Self { events, source }
+    }
+}
+
+impl<'a, I> Iterator for MathToMathml<'_, I>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        match self.events.next() {
+            Some(Event::InlineMath(tex)) => match render(&tex, false) {
+                Ok(mathml) => Some(Event::InlineHtml(mathml.into())),
+                Err(err) => {
+                    log::warn!("Unsupported math `{tex}` in {}: {err}", self.source);
+                    Some(Event::InlineMath(tex))
+                }
+            },
+            Some(Event::DisplayMath(tex)) => match render(&tex, true) {
+                Ok(mathml) => Some(Event::InlineHtml(mathml.into())),
+                Err(err) => {
+                    log::warn!("Unsupported math `{tex}` in {}: {err}", self.source);
+                    Some(Event::DisplayMath(tex))
+                }
+            },
+            item => item,
+        }
+    }
+}
+
+#[cfg(feature = "math")]
+fn render(tex: &str, display: bool) -> Result<String, String> {
+    let mut opts = katex::Opts::default();
+    opts.set_display_mode(display);
+    opts.set_output_type(katex::OutputType::Mathml);
+    opts.set_throw_on_error(true);
+    katex::render_with_opts(tex, &opts).map_err(|err| match err {
+        katex::Error::JsExecError(detail) => summarize_parse_error(&detail),
+        err => err.to_string(),
+    })
+}
+
+/// Trim `KaTeX`'s `ParseError` down to the message, dropping the underlined excerpt
+#[cfg(feature = "math")]
+fn summarize_parse_error(detail: &str) -> String {
+    let Some((_, message)) = detail.split_once("ParseError: ") else {
+        return detail.to_owned();
+    };
+    const POSITION: &str = " at position ";
+    let message = match message.find(POSITION) {
+        Some(start) => {
+            let end = start + POSITION.len();
+            let digits = message[end..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(message.len() - end);
+            &message[..end + digits]
+        }
+        None => message.trim_end_matches(['"', ')']),
+    };
+    message.replace("\\\\", "\\")
+}
+
+#[cfg(not(feature = "math"))]
+fn render(_tex: &str, _display: bool) -> Result<String, String> {
+    Err("math rendering is unsupported in this build".to_owned())
+}
+
+/// Reject `markdown.math` up front when this build can't convert it
+pub(crate) fn ensure_supported(markdown: &cobalt_config::Markdown) -> crate::error::Result<()> {
+    if cfg!(not(feature = "math")) && markdown.math == Some(true) {
+        anyhow::bail!("`markdown.math` requires cobalt to be built with the `math` feature");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(feature = "math")]
+mod test {
+    use super::*;
+
+    use pulldown_cmark as cmark;
+
+    fn render_markdown(content: &str) -> String {
+        let parser = cmark::Parser::new_ext(content, cmark::Options::ENABLE_MATH);
+        let mut html = String::new();
+        cmark::html::push_html(&mut html, MathToMathml::new(parser, "test.md"));
+        html
+    }
+
+    #[test]
+    fn inline_math_is_mathml() {
+        let html = render_markdown("Euler: $e^{i\\pi}$");
+        assert!(
+            html.starts_with("<p>Euler: <span class=\"katex\"><math"),
+            "{html}"
+        );
+        assert!(html.contains("<msup>"), "{html}");
+        assert!(!html.contains("math-inline"), "{html}");
+    }
+
+    #[test]
+    fn display_math_is_block() {
+        let html = render_markdown("$$\\frac{1}{2}$$");
+        assert!(html.contains("display=\"block\""), "{html}");
+        assert!(html.contains("<mfrac>"), "{html}");
+    }
+
+    #[test]
+    fn unsupported_math_is_kept() {
+        let html = render_markdown("$\\unknowncommand{x}$");
+        assert_eq!(
+            html,
+            "<p><span class=\"math math-inline\">\\unknowncommand{x}</span></p>\n"
+        );
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(feature = "math"))]
+mod test_unsupported {
+    use super::*;
+
+    #[test]
+    fn math_is_rejected() {
+        let markdown = cobalt_config::Markdown {
+            math: Some(true),
+            ..Default::default()
+        };
+        assert!(ensure_supported(&markdown).is_err());
+        assert!(ensure_supported(&cobalt_config::Markdown::default()).is_ok());
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use liquid_core::Expression;
+use liquid_core::Language;
+use liquid_core::TagBlock;
+use liquid_core::TagTokenIter;
+use liquid_core::error::ResultLiquidExt;
+use liquid_core::model::KString;
+use liquid_core::runtime::StackFrame;
+use liquid_core::{Renderable, Runtime};
+
+pub(crate) type ShortcodeMap = Arc<Shortcodes>;
+
+/// The templates in `_shortcodes`, by name
+///
+/// These are kept apart from `_includes` so `{% include %}` can't reach them. Each is compiled
+/// the first time it is used.
+#[derive(Debug, Default)]
+pub(crate) struct Shortcodes {
+    sources: HashMap<KString, String>,
+    /// `None` while a shortcode is being compiled, to catch shortcodes using themselves
+    compiled: Mutex<HashMap<KString, Option<Arc<liquid_core::Template>>>>,
+}
+
+impl Shortcodes {
+    /// Returns `false` if `name` is already defined
+    pub(crate) fn insert(&mut self, name: KString, source: String) -> bool {
+        match self.sources.entry(name) {
+            std::collections::hash_map::Entry::Occupied(_) => false,
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(source);
+                true
+            }
+        }
+    }
+
+    fn compile(
+        &self,
+        name: &KString,
+        options: &Language,
+    ) -> Result<Arc<liquid_core::Template>, liquid_core::Error> {
+        let source = self.sources.get(name).ok_or_else(|| {
+            let mut available: Vec<_> = self.sources.keys().map(|k| k.as_str()).collect();
+            available.sort_unstable();
+            liquid_core::Error::with_msg("Unknown shortcode")
+                .context("requested shortcode", name.clone())
+                .context("available shortcodes", itertools::join(available, ", "))
+        })?;
+
+        match self
+            .compiled
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(name.clone())
+        {
+            std::collections::hash_map::Entry::Occupied(entry) => {
+                return entry.get().clone().ok_or_else(|| {
+                    liquid_core::Error::with_msg("Shortcode uses itself")
+                        .context("shortcode", name.clone())
+                });
+            }
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(None);
+            }
+        }
+        let template = liquid_core::parser::parse(source, options)
+            .map(|elements| Arc::new(liquid_core::Template::new(elements)))
+            .trace_with(|| format!("{{% shortcode {name} %}}").into());
+        let mut compiled = self.compiled.lock().unwrap_or_else(PoisonError::into_inner);
+        match &template {
+            Ok(template) => {
+                compiled.insert(name.clone(), Some(template.clone()));
+            }
+            Err(_) => {
+                compiled.remove(name);
+            }
+        }
+        template
+    }
+}
+
+#[derive(Debug)]
+struct Shortcode {
+    name: KString,
+    template: Arc<liquid_core::Template>,
+    args: Vec<(KString, Expression)>,
+    content: Option<liquid_core::Template>,
+}
+
+impl Renderable for Shortcode {
+    fn render_to(
+        &self,
+        writer: &mut dyn Write,
+        runtime: &dyn Runtime,
+    ) -> Result<(), liquid_core::Error> {
+        let mut shortcode = liquid::Object::new();
+        shortcode.insert(
+            "name".into(),
+            liquid::model::Value::scalar(self.name.clone()),
+        );
+        for (id, val) in &self.args {
+            let value = val.evaluate(runtime)?.into_owned();
+            shortcode.insert(id.clone(), value);
+        }
+        if let Some(content) = &self.content {
+            let content = content.render(runtime)?;
+            shortcode.insert("content".into(), liquid::model::Value::scalar(content));
+        }
+
+        let mut pass_through = liquid::Object::new();
+        pass_through.insert("shortcode".into(), liquid::model::Value::Object(shortcode));
+        let scope = StackFrame::new(runtime, &pass_through);
+        self.template
+            .render_to(writer, &scope)
+            .trace_with(|| format!("{{% shortcode {} %}}", self.name).into())?;
+
+        Ok(())
+    }
+}
+
+fn parse_shortcode(
+    shortcodes: &ShortcodeMap,
+    mut arguments: TagTokenIter<'_>,
+    options: &Language,
+) -> Result<Shortcode, liquid_core::Error> {
+    let name = arguments.expect_next("Shortcode name expected.")?;
+    let name = KString::from_ref(name.as_str().trim_matches(|c| c == '"' || c == '\''));
+    let template = shortcodes.compile(&name, options)?;
+
+    let mut args = Vec::new();
+    while let Ok(next) = arguments.expect_next("") {
+        let id = next.expect_identifier().into_result()?.to_string();
+
+        arguments
+            .expect_next("\":\" expected.")?
+            .expect_str(":")
+            .into_result_custom_msg("expected \":\" to be used for the assignment")?;
+
+        args.push((
+            id.into(),
+            arguments
+                .expect_next("expected value")?
+                .expect_value()
+                .into_result()?,
+        ));
+
+        if let Ok(comma) = arguments.expect_next("") {
+            // stop looking for arguments if there is no comma
+            if comma.expect_str(",").into_result().is_err() {
+                break;
+            }
+        }
+    }
+    arguments.expect_nothing()?;
+
+    Ok(Shortcode {
+        name,
+        template,
+        args,
+        content: None,
+    })
+}
+
+/// Inline shortcode, e.g. `{% shortcode youtube id: "abc" %}`
+#[derive(Clone, Debug)]
+pub(crate) struct ShortcodeParser {
+    shortcodes: ShortcodeMap,
+}
+
+impl ShortcodeParser {
+    pub(crate) fn new(shortcodes: ShortcodeMap) -> Self {
+        Self { shortcodes }
+    }
+}
+
+impl liquid_core::TagReflection for ShortcodeParser {
+    fn tag(&self) -> &'static str {
+        "shortcode"
+    }
+
+    fn description(&self) -> &'static str {
+        "Render a template from `_shortcodes`"
+    }
+}
+
+impl liquid_core::ParseTag for ShortcodeParser {
+    fn reflection(&self) -> &dyn liquid_core::TagReflection {
+        self
+    }
+
+    fn parse(
+        &self,
+        arguments: TagTokenIter<'_>,
+        options: &Language,
+    ) -> Result<Box<dyn Renderable>, liquid_core::Error> {
+        let shortcode = parse_shortcode(&self.shortcodes, arguments, options)?;
+        Ok(Box::new(shortcode))
+    }
+}
+
+/// Shortcode wrapping content, e.g. `{% shortcodeblock note %}...{% endshortcodeblock %}`
+#[derive(Clone, Debug)]
+pub(crate) struct ShortcodeBlockParser {
+    shortcodes: ShortcodeMap,
+}
+
+impl ShortcodeBlockParser {
+    pub(crate) fn new(shortcodes: ShortcodeMap) -> Self {
+        Self { shortcodes }
+    }
+}
+
+impl liquid_core::BlockReflection for ShortcodeBlockParser {
+    fn start_tag(&self) -> &'static str {
+        "shortcodeblock"
+    }
+
+    fn end_tag(&self) -> &'static str {
+        "endshortcodeblock"
+    }
+
+    fn description(&self) -> &'static str {
+        "Render a template from `_shortcodes`, passing the block as `shortcode.content`"
+    }
+}
+
+impl liquid_core::ParseBlock for ShortcodeBlockParser {
+    fn reflection(&self) -> &dyn liquid_core::BlockReflection {
+        self
+    }
+
+    fn parse(
+        &self,
+        arguments: TagTokenIter<'_>,
+        mut tokens: TagBlock<'_, '_>,
+        options: &Language,
+    ) -> Result<Box<dyn Renderable>, liquid_core::Error> {
+        let mut shortcode = parse_shortcode(&self.shortcodes, arguments, options)?;
+
+        let content = tokens.parse_all(options)?;
+        tokens.assert_empty();
+        shortcode.content = Some(liquid_core::Template::new(content));
+
+        Ok(Box::new(shortcode))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parser(shortcodes: &[(&str, &str)]) -> liquid::Parser {
+        let mut partials =
+            liquid::partials::EagerCompiler::<liquid::partials::InMemorySource>::empty();
+        partials.add("_shortcodes/video.liquid", "included");
+        let mut names = Shortcodes::default();
+        for (name, source) in shortcodes {
+            names.insert(KString::from_ref(name), (*source).to_owned());
+        }
+        let names = Arc::new(names);
+        liquid::ParserBuilder::with_stdlib()
+            .tag(ShortcodeParser::new(names.clone()))
+            .block(ShortcodeBlockParser::new(names))
+            .partials(partials)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn inline_shortcode_renders_args() {
+        let parser = parser(&[("video", r#"<video src="{{ shortcode.src }}"></video>"#)]);
+        let template = parser
+            .parse(r#"{% shortcode video src: "a.mp4" %}"#)
+            .unwrap();
+        let output = template.render(&liquid::Object::new()).unwrap();
+        assert_eq!(output, r#"<video src="a.mp4"></video>"#);
+    }
+
+    #[test]
+    fn block_shortcode_renders_content() {
+        let parser = parser(&[(
+            "note",
+            r#"<aside class="{{ shortcode.kind }}">{{ shortcode.content }}</aside>"#,
+        )]);
+        let template = parser
+            .parse(r#"{% shortcodeblock note kind: "warn" %}Hi {{ who }}{% endshortcodeblock %}"#)
+            .unwrap();
+        let globals: liquid::Object = [("who".into(), liquid::model::Value::scalar("there"))]
+            .into_iter()
+            .collect();
+        let output = template.render(&globals).unwrap();
+        assert_eq!(output, r#"<aside class="warn">Hi there</aside>"#);
+    }
+
+    #[test]
+    fn unknown_shortcode_fails_to_parse() {
+        let parser = parser(&[]);
+        assert!(parser.parse("{% shortcode missing %}").is_err());
+    }
+
+    #[test]
+    fn nested_shortcodes() {
+        let parser = parser(&[
+            (
+                "figure",
+                "<figure>{% shortcode caption text: shortcode.alt %}</figure>",
+            ),
+            ("caption", "<figcaption>{{ shortcode.text }}</figcaption>"),
+            ("loop", "{% shortcode loop %}"),
+        ]);
+        let template = parser
+            .parse(r#"{% shortcode figure alt: "Cat" %}"#)
+            .unwrap();
+        let output = template.render(&liquid::Object::new()).unwrap();
+        assert_eq!(output, "<figure><figcaption>Cat</figcaption></figure>");
+
+        let Err(recursive) = parser.parse("{% shortcode loop %}") else {
+            panic!("recursive shortcodes should fail to parse");
+        };
+        assert!(recursive.to_string().contains("Shortcode uses itself"));
+    }
+
+    #[test]
+    fn shortcodes_are_not_includes() {
+        let parser = parser(&[("video", "shortcode")]);
+        let template = parser
+            .parse(r#"{% include "_shortcodes/video.liquid" %}|{% shortcode video %}"#)
+            .unwrap();
+        let output = template.render(&liquid::Object::new()).unwrap();
+        assert_eq!(output, "included|shortcode");
+    }
+}
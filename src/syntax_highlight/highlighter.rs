@@ -0,0 +1,243 @@
+use std::hash::{DefaultHasher, Hash as _, Hasher as _};
+use std::path::Path;
+
+use anyhow::Context as _;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::html::{
+    ClassStyle, ClassedHTMLGenerator, IncludeBackground, append_highlighted_html_for_styled_line,
+    css_for_theme_with_class_style, start_highlighted_html_snippet,
+};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use crate::error::Result;
+
+const CSS_THEME: &str = "css";
+
+/// Highlight code blocks with the built-in syntaxes and themes plus any the project provides
+#[derive(Debug)]
+pub struct SyntaxHighlight {
+    syntax_set: SyntaxSet,
+    theme_set: ThemeSet,
+    default_theme: Option<String>,
+    /// Hashes the project's syntaxes and themes
+    custom: DefaultHasher,
+}
+
+impl SyntaxHighlight {
+    pub fn new() -> Self {
+        Self {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme_set: ThemeSet::load_defaults(),
+            default_theme: None,
+            custom: DefaultHasher::new(),
+        }
+    }
+
+    /// Identifies the syntaxes and themes, which change what highlighted code compiles to
+    pub fn fingerprint(&self) -> u64 {
+        self.custom.finish()
+    }
+
+    /// Add every `.sublime-syntax` file under `syntaxes_path`
+    pub fn load_custom_syntaxes(&mut self, syntaxes_path: &Path) -> Result<()> {
+        let mut builder = self.syntax_set.clone().into_builder();
+        builder
+            .add_from_folder(syntaxes_path, true)
+            .with_context(|| {
+                format!("Failed to load syntaxes from `{}`", syntaxes_path.display())
+            })?;
+        self.syntax_set = builder.build();
+        hash_folder(syntaxes_path, "sublime-syntax", &mut self.custom)
+    }
+
+    /// Add every `.tmTheme` file under `themes_path`, named after the file
+    pub fn load_custom_themes(&mut self, themes_path: &Path) -> Result<()> {
+        self.theme_set
+            .add_from_folder(themes_path)
+            .with_context(|| format!("Failed to load themes from `{}`", themes_path.display()))?;
+        hash_folder(themes_path, "tmTheme", &mut self.custom)
+    }
+
+    pub fn css_theme_name() -> &'static str {
+        CSS_THEME
+    }
+
+    pub fn has_theme(&self, name: &str) -> bool {
+        name == CSS_THEME || self.theme_set.themes.contains_key(name)
+    }
+
+    pub fn themes(&self) -> impl Iterator<Item = String> + '_ {
+        let mut themes: Vec<_> = self.theme_set.themes.keys().cloned().collect();
+        themes.push(CSS_THEME.to_owned());
+        themes.sort_by_key(|a| a.to_ascii_lowercase());
+
+        themes.into_iter()
+    }
+
+    fn css_class_style() -> ClassStyle {
+        ClassStyle::SpacedPrefixed { prefix: "c-" }
+    }
+
+    /// Get the content of a css file to apply the specified color theme when using the 'css' theme
+    pub fn css_for_theme(&self, name: &str) -> String {
+        let theme = &self.theme_set.themes[name];
+        css_for_theme_with_class_style(theme, Self::css_class_style()).unwrap()
+    }
+
+    pub fn syntaxes(&self) -> impl Iterator<Item = String> + '_ {
+        fn reference_to_string(sd: &SyntaxReference) -> String {
+            let extensions = sd.file_extensions.join(", ");
+            format!("{} [{}]", sd.name, extensions)
+        }
+
+        let mut syntaxes = self
+            .syntax_set
+            .syntaxes()
+            .iter()
+            .map(reference_to_string)
+            .collect::<Vec<_>>();
+
+        // sort alphabetically with insensitive ascii case
+        syntaxes.sort_by_key(|a| a.to_ascii_lowercase());
+
+        syntaxes.into_iter()
+    }
+
+    pub fn default_theme(&self) -> Option<&str> {
+        self.default_theme.as_deref()
+    }
+
+    pub fn set_default_theme(&mut self, theme: impl Into<String>) {
+        self.default_theme = Some(theme.into());
+    }
+
+    fn format_inline_theme(&self, code: &str, theme: &str, syntax: &SyntaxReference) -> String {
+        let theme = &self.theme_set.themes[theme];
+
+        // Essentially the same as `syntect::html::highlighted_html_for_string`,
+        // but adding <code> tags between the <pre> tags
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let (mut output, bg) = start_highlighted_html_snippet(theme);
+        output.push_str("<code>");
+
+        for line in LinesWithEndings::from(code) {
+            let regions = highlighter.highlight_line(line, &self.syntax_set).unwrap();
+            append_highlighted_html_for_styled_line(
+                &regions[..],
+                IncludeBackground::IfDifferent(bg),
+                &mut output,
+            )
+            .unwrap();
+        }
+
+        output.push_str("</code></pre>\n");
+        output
+    }
+
+    fn format_css_theme(&self, code: &str, lang: Option<&str>, syntax: &SyntaxReference) -> String {
+        let mut html_generator = ClassedHTMLGenerator::new_with_class_style(
+            syntax,
+            &self.syntax_set,
+            Self::css_class_style(),
+        );
+
+        for line in LinesWithEndings::from(code) {
+            html_generator
+                .parse_html_for_line_which_includes_newline(line)
+                .unwrap();
+        }
+
+        let language_class = lang.map(|l| format!("language-{l} ")).unwrap_or_default();
+        let mut output = format!("<pre class=\"{language_class}highlighter-syntect\">");
+        output.push_str("<code class=\"highlight\">");
+        output.push_str(&html_generator.finalize());
+        output.push_str("</code></pre>");
+
+        output
+    }
+
+    pub fn format(&self, code: &str, lang: Option<&str>, theme: Option<&str>) -> String {
+        if let Some(theme) = theme.or_else(|| self.default_theme()) {
+            let syntax = lang
+                .and_then(|l| self.syntax_set.find_syntax_by_token(l))
+                .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
+
+            if theme == CSS_THEME {
+                self.format_css_theme(code, lang, syntax)
+            } else {
+                self.format_inline_theme(code, theme, syntax)
+            }
+        } else {
+            super::raw::format(code, lang)
+        }
+    }
+}
+
+fn hash_folder(path: &Path, extension: &str, hasher: &mut DefaultHasher) -> Result<()> {
+    for entry in walkdir::WalkDir::new(path).sort_by_file_name() {
+        let entry = entry?;
+        if entry.path().extension().is_some_and(|ext| ext == extension) {
+            entry.path().hash(hasher);
+            std::fs::read(entry.path())?.hash(hasher);
+        }
+    }
+    Ok(())
+}
+
+impl Default for SyntaxHighlight {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CUSTOM_CODEBLOCK: &str = "[[[]]]]";
+
+    #[test]
+    fn highlight_custom_syntax_and_theme() {
+        let mut syntax = SyntaxHighlight::new();
+        syntax
+            .load_custom_syntaxes(Path::new("tests/fixtures/highlight/_syntaxes"))
+            .unwrap();
+        syntax
+            .load_custom_themes(Path::new("tests/fixtures/highlight/_themes"))
+            .unwrap();
+        assert!(syntax.syntaxes().any(|s| s == "Brackets [brackets]"));
+        assert!(syntax.has_theme("brand"));
+        assert!(syntax.themes().any(|t| t == "brand"));
+
+        let output = syntax.format(CUSTOM_CODEBLOCK, Some("brackets"), Some("brand"));
+        assert_eq!(
+            output,
+            "<pre style=\"background-color:#1d1f3c;\">\n\
+             <code><span style=\"color:#f5f5f5;\">[[[]]]</span>\
+             <span style=\"color:#ff5f00;\">]</span></code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn fingerprint_custom_themes() {
+        let mut syntax = SyntaxHighlight::new();
+        let builtin = syntax.fingerprint();
+        assert_eq!(builtin, SyntaxHighlight::new().fingerprint());
+        syntax
+            .load_custom_themes(Path::new("tests/fixtures/highlight/_themes"))
+            .unwrap();
+        assert_ne!(syntax.fingerprint(), builtin);
+    }
+
+    #[test]
+    fn load_missing_directory() {
+        let mut syntax = SyntaxHighlight::new();
+        assert!(
+            syntax
+                .load_custom_themes(Path::new("tests/fixtures/highlight/missing"))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::io::Write;
+use std::path;
+
+use liquid_core::Expression;
+use liquid_core::Language;
+use liquid_core::TagTokenIter;
+use liquid_core::ValueView;
+use liquid_core::error::ResultLiquidExt;
+use liquid_core::{Renderable, Runtime};
+
+use super::CodeBlock;
+use super::SyntaxHighlight;
+use super::lines::CodeOptions;
+use super::lines::parse_range;
+use crate::error;
+
+/// Highlight a project file, e.g. `{% include_code "examples/main.rs" lines="10-25" %}`
+#[derive(Clone, Debug)]
+pub(crate) struct IncludeCodeParser {
+    syntax: std::sync::Arc<SyntaxHighlight>,
+    syntax_theme: Option<liquid::model::KString>,
+    root: path::PathBuf,
+}
+
+impl IncludeCodeParser {
+    pub(crate) fn new(
+        syntax: std::sync::Arc<SyntaxHighlight>,
+        theme: Option<liquid::model::KString>,
+        root: path::PathBuf,
+    ) -> Self {
+        Self {
+            syntax,
+            syntax_theme: theme,
+            root,
+        }
+    }
+}
+
+impl liquid_core::TagReflection for IncludeCodeParser {
+    fn tag(&self) -> &'static str {
+        "include_code"
+    }
+
+    fn description(&self) -> &'static str {
+        "Syntax highlight a file from the project"
+    }
+}
+
+impl liquid_core::ParseTag for IncludeCodeParser {
+    fn reflection(&self) -> &dyn liquid_core::TagReflection {
+        self
+    }
+
+    fn parse(
+        &self,
+        mut arguments: TagTokenIter<'_>,
+        _options: &Language,
+    ) -> Result<Box<dyn Renderable>, liquid_core::Error> {
+        let path = arguments
+            .expect_next("File path expected.")?
+            .expect_value()
+            .into_result()?;
+
+        let mut args = Vec::new();
+        let mut arguments = arguments.peekable();
+        while let Some(key) = arguments.next() {
+            let key = key.expect_identifier().into_result()?.to_owned();
+            let value = if arguments.next_if(|token| token.as_str() == "=").is_some() {
+                let value = arguments
+                    .next()
+                    .ok_or_else(|| {
+                        liquid_core::Error::with_msg("Value expected.")
+                            .context("option", key.clone())
+                    })?
+                    .expect_value()
+                    .into_result()?;
+                Some(value)
+            } else {
+                None
+            };
+            args.push((key, value));
+        }
+
+        Ok(Box::new(IncludeCode {
+            syntax: self.syntax.clone(),
+            theme: self.syntax_theme.clone(),
+            root: self.root.clone(),
+            path,
+            args,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct IncludeCode {
+    syntax: std::sync::Arc<SyntaxHighlight>,
+    theme: Option<liquid::model::KString>,
+    root: path::PathBuf,
+    path: Expression,
+    args: Vec<(String, Option<Expression>)>,
+}
+
+impl IncludeCode {
+    fn code_block(&self, runtime: &dyn Runtime) -> error::Result<CodeBlock> {
+        let rel_path = self.path.evaluate(runtime)?.to_kstr().into_owned();
+        let file_path = cobalt_config::RelPath::try_from(rel_path.as_str())
+            .ok()
+            .filter(|path| !path.as_str().starts_with(".."))
+            .ok_or_else(|| anyhow::format_err!("`{rel_path}` is outside the project"))?;
+        let content =
+            std::fs::read_to_string(self.root.join(file_path.as_path())).map_err(|e| {
+                match e.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        anyhow::format_err!("`{rel_path}` does not exist")
+                    }
+                    _ => anyhow::format_err!("Failed to read `{rel_path}`: {e}"),
+                }
+            })?;
+        let mut lines: Vec<&str> = content.lines().collect();
+
+        let mut lang = file_path.extension().map(liquid::model::KString::from_ref);
+        let mut first_line = 1;
+        let mut options = CodeOptions::default();
+        for (key, value) in &self.args {
+            let value = value
+                .as_ref()
+                .map(|value| -> error::Result<_> {
+                    Ok(value.evaluate(runtime)?.to_kstr().into_owned())
+                })
+                .transpose()?;
+            match (key.as_str(), value) {
+                ("lang", Some(value)) => lang = Some(value),
+                ("lines", Some(value)) => {
+                    let range = parse_range(&value)?;
+                    if *range.start() == 0 || lines.len() < *range.end() {
+                        anyhow::bail!(
+                            "Lines {value} are outside `{rel_path}`, which has {} lines",
+                            lines.len()
+                        );
+                    }
+                    first_line += range.start() - 1;
+                    lines = lines[range.start() - 1..*range.end()].to_vec();
+                }
+                ("region", Some(value)) => {
+                    let (offset, region) = region(&lines, &value).ok_or_else(|| {
+                        anyhow::format_err!("No region `{value}` in `{rel_path}`")
+                    })?;
+                    first_line += offset;
+                    lines = region;
+                }
+                (key, value) => options.set(key, value.as_deref())?,
+            }
+        }
+        if options.start.is_none() {
+            options.start = Some(first_line);
+        }
+
+        let mut code = lines.join("\n");
+        code.push('\n');
+        Ok(CodeBlock {
+            syntax: self.syntax.clone(),
+            lang,
+            code,
+            theme: self.theme.clone(),
+            options,
+        })
+    }
+}
+
+impl Renderable for IncludeCode {
+    fn render_to(
+        &self,
+        writer: &mut dyn Write,
+        runtime: &dyn Runtime,
+    ) -> Result<(), liquid_core::Error> {
+        self.code_block(runtime)
+            .map_err(|e| liquid_core::Error::with_msg(e.to_string()))
+            .and_then(|block| block.render_to(writer, runtime))
+            .trace_with(|| "{% include_code %}".into())
+    }
+}
+
+/// The lines between `ANCHOR: name` and `ANCHOR_END: name` markers, without any marker lines,
+/// and the offset of the first
+fn region<'c>(lines: &[&'c str], name: &str) -> Option<(usize, Vec<&'c str>)> {
+    let is_marker = |line: &str, marker: &str| {
+        line.split_once(marker)
+            .is_some_and(|(_, rest)| rest.trim().trim_end_matches("*/").trim_end() == name)
+    };
+    let start = lines.iter().position(|line| is_marker(line, "ANCHOR:"))? + 1;
+    let len = lines[start..]
+        .iter()
+        .position(|line| is_marker(line, "ANCHOR_END:"))?;
+    let region = lines[start..start + len]
+        .iter()
+        .filter(|line| !line.contains("ANCHOR:") && !line.contains("ANCHOR_END:"))
+        .copied()
+        .collect();
+    Some((start, region))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "fn main() {
+    // ANCHOR: setup
+    let a = 1;
+    // ANCHOR: inner
+    let b = a;
+    // ANCHOR_END: inner
+    // ANCHOR_END: setup
+    println!(\"{b}\");
+}
+";
+
+    fn render(template: &str) -> Result<String, liquid_core::Error> {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("examples")).unwrap();
+        std::fs::write(root.path().join("examples/main.rs"), EXAMPLE).unwrap();
+
+        let syntax = std::sync::Arc::new(SyntaxHighlight::new());
+        let parser = liquid::ParserBuilder::new()
+            .tag(IncludeCodeParser::new(syntax, None, root.path().to_owned()))
+            .build()
+            .unwrap();
+        parser.parse(template)?.render(&liquid::Object::new())
+    }
+
+    #[test]
+    fn include_lines() {
+        let html = render(r#"{% include_code "examples/main.rs" lines="3-5" linenos %}"#).unwrap();
+        assert!(
+            html.starts_with("<pre><code class=\"language-rs\">"),
+            "{html}"
+        );
+        assert!(
+            html.contains("<span class=\"lineno\">3</span>    let a = 1;"),
+            "{html}"
+        );
+        assert!(
+            html.contains("<span class=\"lineno\">5</span>    let b = a;"),
+            "{html}"
+        );
+        assert!(!html.contains("<span class=\"lineno\">6</span>"), "{html}");
+    }
+
+    #[test]
+    fn include_region() {
+        let html =
+            render(r#"{% include_code "examples/main.rs" region="setup" lang="rust" %}"#).unwrap();
+        assert_eq!(
+            html,
+            "<pre><code class=\"language-rust\">    let a = 1;\n    let b = a;\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn include_errors() {
+        let missing = render(r#"{% include_code "examples/missing.rs" %}"#).unwrap_err();
+        assert!(
+            missing
+                .to_string()
+                .contains("`examples/missing.rs` does not exist")
+        );
+        let range = render(r#"{% include_code "examples/main.rs" lines="8-20" %}"#).unwrap_err();
+        assert!(
+            range.to_string().contains("Lines 8-20 are outside"),
+            "{range}"
+        );
+        let region = render(r#"{% include_code "examples/main.rs" region="nope" %}"#).unwrap_err();
+        assert!(region.to_string().contains("No region `nope`"));
+        let outside = render(r#"{% include_code "../secret.rs" %}"#).unwrap_err();
+        assert!(outside.to_string().contains("outside the project"));
+    }
+}
@@ -0,0 +1,195 @@
+use std::ops::RangeInclusive;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::error::Result;
+
+/// Per-block options following the language, e.g. `rust,linenos,hl_lines=3-5,start=10`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct CodeOptions {
+    /// Number each line
+    pub(crate) linenos: bool,
+    /// Lines to emphasize, by their displayed number
+    pub(crate) hl_lines: Vec<RangeInclusive<usize>>,
+    /// The number of the first line, 1 when unset
+    pub(crate) start: Option<usize>,
+}
+
+impl CodeOptions {
+    /// Split an info string into the language and the options after it
+    ///
+    /// Options are separated by commas; `hl_lines` takes space-separated lines or ranges.  Flags
+    /// meant for other tools, like rustdoc's `ignore` and `no_run`, are skipped.
+    pub(crate) fn parse_info(info: &str) -> Result<(Option<&str>, Self)> {
+        let mut parts = info.split(',');
+        let lang = parts.next().and_then(|lang| lang.split_whitespace().next());
+        let mut options = Self::default();
+        for part in parts.map(str::trim).filter(|part| !part.is_empty()) {
+            match part.split_once('=') {
+                Some((key, value)) => options.set(key.trim(), Some(value.trim()))?,
+                None => options.set(part, None)?,
+            }
+        }
+        Ok((lang, options))
+    }
+
+    /// Apply one option, `value` being what follows `=`
+    pub(crate) fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        match (key, value) {
+            ("linenos", None) => self.linenos = true,
+            ("hl_lines", Some(value)) => {
+                for lines in value.split_whitespace() {
+                    self.hl_lines.push(parse_range(lines)?);
+                }
+            }
+            ("start", Some(value)) => {
+                let start = value
+                    .parse()
+                    .map_err(|_| anyhow::format_err!("Invalid start line `{value}`"))?;
+                self.start = Some(start);
+            }
+            (key, None) => log::debug!("Ignoring unknown code block option `{key}`"),
+            (key, Some(value)) => {
+                log::debug!("Ignoring unknown code block option `{key}={value}`");
+            }
+        }
+        Ok(())
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.hl_lines.iter().any(|lines| lines.contains(&line))
+    }
+
+    /// Wrap each line of a highlighted block in `<span class="line">`, with its number and
+    /// emphasis
+    ///
+    /// Spans left open at the end of a line are closed and reopened on the next so each line
+    /// stays well-formed.
+    pub(crate) fn decorate(&self, html: String) -> String {
+        static SPAN: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"<span[^>]*>|</span>").unwrap());
+
+        if !self.linenos && self.hl_lines.is_empty() {
+            return html;
+        }
+        let Some(start) = html
+            .find("<code")
+            .and_then(|code| Some(code + html[code..].find('>')? + 1))
+        else {
+            return html;
+        };
+        let Some(end) = html.rfind("</code>").filter(|end| start <= *end) else {
+            return html;
+        };
+
+        let mut output = html[..start].to_owned();
+        let mut open: Vec<&str> = Vec::new();
+        for (number, line) in
+            (self.start.unwrap_or(1)..).zip(html[start..end].split_inclusive('\n'))
+        {
+            let (content, newline) = match line.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (line, ""),
+            };
+            if newline.is_empty() && SPAN.replace_all(content, "").is_empty() {
+                // Only the tags closing the last line
+                break;
+            }
+            output.push_str(if self.is_highlighted(number) {
+                "<span class=\"line hl\">"
+            } else {
+                "<span class=\"line\">"
+            });
+            if self.linenos {
+                output.push_str(&format!("<span class=\"lineno\">{number}</span>"));
+            }
+            for tag in &open {
+                output.push_str(tag);
+            }
+            output.push_str(content);
+            for tag in SPAN.find_iter(content) {
+                if tag.as_str() == "</span>" {
+                    open.pop();
+                } else {
+                    open.push(tag.as_str());
+                }
+            }
+            output.push_str(&"</span>".repeat(open.len()));
+            output.push_str("</span>");
+            output.push_str(newline);
+        }
+        output.push_str(&html[end..]);
+        output
+    }
+}
+
+pub(super) fn parse_range(lines: &str) -> Result<RangeInclusive<usize>> {
+    let parse = |line: &str| {
+        line.parse::<usize>()
+            .map_err(|_| anyhow::format_err!("Invalid line range `{lines}`"))
+    };
+    match lines.split_once('-') {
+        Some((first, last)) => Ok(parse(first)?..=parse(last)?),
+        None => {
+            let line = parse(lines)?;
+            Ok(line..=line)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_info_options() {
+        let (lang, options) =
+            CodeOptions::parse_info("rust,linenos,hl_lines=3-5 7,start=10").unwrap();
+        assert_eq!(lang, Some("rust"));
+        assert_eq!(
+            options,
+            CodeOptions {
+                linenos: true,
+                hl_lines: vec![3..=5, 7..=7],
+                start: Some(10),
+            }
+        );
+
+        let (lang, options) = CodeOptions::parse_info("python extra").unwrap();
+        assert_eq!(lang, Some("python"));
+        assert_eq!(options, CodeOptions::default());
+
+        let (lang, options) =
+            CodeOptions::parse_info("rust,ignore,no_run,should_panic,edition=2021").unwrap();
+        assert_eq!(lang, Some("rust"));
+        assert_eq!(options, CodeOptions::default());
+
+        assert!(CodeOptions::parse_info("rust,hl_lines=a-3").is_err());
+        assert!(CodeOptions::parse_info("rust,start=ten").is_err());
+    }
+
+    #[test]
+    fn decorate_reopens_spans() {
+        let options = CodeOptions {
+            linenos: true,
+            hl_lines: vec![2..=2],
+            start: None,
+        };
+        let html = "<pre><code><span class=\"c-a\">a\nb</span>\nc\n</code></pre>\n".to_owned();
+        assert_eq!(
+            options.decorate(html),
+            "<pre><code>\
+             <span class=\"line\"><span class=\"lineno\">1</span><span class=\"c-a\">a</span></span>\n\
+             <span class=\"line hl\"><span class=\"lineno\">2</span><span class=\"c-a\">b</span></span>\n\
+             <span class=\"line\"><span class=\"lineno\">3</span>c</span>\n\
+             </code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn decorate_without_options_is_unchanged() {
+        let html = "<pre><code>a\n</code></pre>\n".to_owned();
+        assert_eq!(CodeOptions::default().decorate(html.clone()), html);
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::Write;
+use std::path::Path;
 
 use crate::error;
 use itertools::Itertools;
@@ -12,10 +13,20 @@ use liquid_core::{Renderable, Runtime};
 use pulldown_cmark as cmark;
 use pulldown_cmark::Event::{self, End, Html, Start, Text};
 
-#[cfg(not(feature = "syntax-highlight"))]
-pub use engarde::Raw as SyntaxHighlight;
 #[cfg(feature = "syntax-highlight")]
-pub use engarde::Syntax as SyntaxHighlight;
+mod highlighter;
+mod include_code;
+mod lines;
+mod raw;
+
+pub(crate) use include_code::IncludeCodeParser;
+
+use lines::CodeOptions;
+
+#[cfg(feature = "syntax-highlight")]
+pub use highlighter::SyntaxHighlight;
+#[cfg(not(feature = "syntax-highlight"))]
+pub use raw::Raw as SyntaxHighlight;
 
 #[derive(Clone, Debug)]
 struct CodeBlock {
@@ -23,6 +34,7 @@ struct CodeBlock {
     lang: Option<liquid::model::KString>,
     code: String,
     theme: Option<liquid::model::KString>,
+    options: CodeOptions,
 }
 
 impl Renderable for CodeBlock {
@@ -34,8 +46,11 @@ impl Renderable for CodeBlock {
         write!(
             writer,
             "{}",
-            self.syntax
-                .format(&self.code, self.lang.as_deref(), self.theme.as_deref())
+            self.options.decorate(self.syntax.format(
+                &self.code,
+                self.lang.as_deref(),
+                self.theme.as_deref()
+            ))
         )
         .replace("Failed to render")?;
 
@@ -75,6 +90,80 @@ fn verify_theme(syntax: &SyntaxHighlight, theme: Option<&str>) -> error::Result<
     Ok(())
 }
 
+/// The built-in syntaxes and themes plus the project's own, where those directories exist
+#[cfg(feature = "syntax-highlight")]
+pub(crate) fn load(syntaxes_path: &Path, themes_path: &Path) -> error::Result<SyntaxHighlight> {
+    let mut highlight = SyntaxHighlight::new();
+    if syntaxes_path.exists() {
+        log::debug!("Loading syntaxes from `{}`", syntaxes_path.display());
+        highlight.load_custom_syntaxes(syntaxes_path)?;
+    }
+    if themes_path.exists() {
+        log::debug!("Loading themes from `{}`", themes_path.display());
+        highlight.load_custom_themes(themes_path)?;
+    }
+    Ok(highlight)
+}
+
+#[cfg(not(feature = "syntax-highlight"))]
+pub(crate) fn load(_syntaxes_path: &Path, _themes_path: &Path) -> error::Result<SyntaxHighlight> {
+    Ok(SyntaxHighlight::new())
+}
+
+/// Identifies the syntaxes and themes, which change what highlighted code compiles to
+#[cfg(feature = "syntax-highlight")]
+pub(crate) fn fingerprint(syntax: &SyntaxHighlight) -> u64 {
+    syntax.fingerprint()
+}
+
+#[cfg(not(feature = "syntax-highlight"))]
+pub(crate) fn fingerprint(_syntax: &SyntaxHighlight) -> u64 {
+    0
+}
+
+/// The theme that has the highlighter emit CSS classes rather than inline styles
+#[cfg(feature = "syntax-highlight")]
+pub(crate) fn classes_theme() -> &'static str {
+    SyntaxHighlight::css_theme_name()
+}
+
+#[cfg(not(feature = "syntax-highlight"))]
+pub(crate) fn classes_theme() -> &'static str {
+    "css"
+}
+
+/// The CSS for highlighting in `classes` mode, with each theme scoped to its media query
+#[cfg(feature = "syntax-highlight")]
+pub(crate) fn stylesheet(
+    syntax: &SyntaxHighlight,
+    themes: &[cobalt_config::StylesheetTheme],
+) -> error::Result<String> {
+    let mut css = String::new();
+    for theme in themes {
+        if theme.theme == classes_theme() || !syntax.has_theme(&theme.theme) {
+            anyhow::bail!("Syntax theme '{}' is unsupported", theme.theme);
+        }
+        let rules = syntax.css_for_theme(&theme.theme);
+        match &theme.media {
+            Some(media) => {
+                css.push_str(&format!("@media {media} {{\n"));
+                css.push_str(&rules);
+                css.push_str("}\n");
+            }
+            None => css.push_str(&rules),
+        }
+    }
+    Ok(css)
+}
+
+#[cfg(not(feature = "syntax-highlight"))]
+pub(crate) fn stylesheet(
+    _syntax: &SyntaxHighlight,
+    _themes: &[cobalt_config::StylesheetTheme],
+) -> error::Result<String> {
+    anyhow::bail!("Themes are unsupported in this build.");
+}
+
 #[cfg(feature = "syntax-highlight")]
 fn has_syntax_theme(syntax: &SyntaxHighlight, name: &str) -> error::Result<bool> {
     Ok(syntax.has_theme(name))
@@ -106,24 +195,32 @@ impl liquid_core::ParseBlock for CodeBlockParser {
 
     fn parse(
         &self,
-        mut arguments: TagTokenIter<'_>,
+        arguments: TagTokenIter<'_>,
         mut tokens: TagBlock<'_, '_>,
         _options: &Language,
     ) -> Result<Box<dyn Renderable>, liquid_core::Error> {
-        let lang = arguments
-            .expect_next("Identifier or literal expected.")
-            .ok()
-            .map(|lang| {
-                // This may accept strange inputs such as `{% include 0 %}` or `{% include filterchain | filter:0 %}`.
-                // Those inputs would fail anyway by there being not a path with those langs so they are not a big concern.
-                match lang.expect_literal() {
-                    // Using `to_str()` on literals ensures `Strings` will have their quotes trimmed.
-                    TryMatchToken::Matches(lang) => lang.to_kstr().into_owned(),
-                    TryMatchToken::Fails(lang) => liquid::model::KString::from_ref(lang.as_str()),
-                }
-            });
-        // no more arguments should be supplied, trying to supply them is an error
-        arguments.expect_nothing()?;
+        // Rebuild the info string, e.g. `rust linenos hl_lines="3-5"` as `rust,linenos,hl_lines=3-5`
+        let mut info = String::new();
+        let mut joined = true;
+        for token in arguments {
+            // This may accept strange inputs such as `{% include 0 %}` or `{% include filterchain | filter:0 %}`.
+            // Those inputs would fail anyway by there being not a path with those langs so they are not a big concern.
+            let token = match token.expect_literal() {
+                // Using `to_str()` on literals ensures `Strings` will have their quotes trimmed.
+                TryMatchToken::Matches(token) => token.to_kstr().into_owned(),
+                TryMatchToken::Fails(token) => liquid::model::KString::from_ref(token.as_str()),
+            };
+            // `-5` ends a range like `3-5`
+            let continues = joined || matches!(token.as_str(), "," | "=") || token.starts_with('-');
+            if !continues {
+                info.push(',');
+            }
+            info.push_str(&token);
+            joined = matches!(token.as_str(), "," | "=");
+        }
+        let (lang, options) = CodeOptions::parse_info(&info)
+            .map_err(|e| liquid_core::Error::with_msg(e.to_string()))?;
+        let lang = lang.map(liquid::model::KString::from_ref);
 
         let mut content = String::new();
         while let Some(element) = tokens.next()? {
@@ -136,21 +233,23 @@ impl liquid_core::ParseBlock for CodeBlockParser {
             code: content,
             lang,
             theme: self.syntax_theme.clone(),
+            options,
         }))
     }
 }
 
-pub(crate) struct DecoratedParser<'a> {
-    parser: cmark::Parser<'a>,
+pub(crate) struct DecoratedParser<'a, I> {
+    parser: I,
     syntax: std::sync::Arc<SyntaxHighlight>,
     theme: Option<&'a str>,
     lang: Option<String>,
+    options: CodeOptions,
     code: Option<Vec<pulldown_cmark::CowStr<'a>>>,
 }
 
-impl<'a> DecoratedParser<'a> {
+impl<'a, I> DecoratedParser<'a, I> {
     pub(crate) fn new(
-        parser: cmark::Parser<'a>,
+        parser: I,
         syntax: std::sync::Arc<SyntaxHighlight>,
         theme: Option<&'a str>,
     ) -> error::Result<Self> {
@@ -160,12 +259,16 @@ impl<'a> DecoratedParser<'a> {
             syntax,
             theme,
             lang: None,
+            options: CodeOptions::default(),
             code: None,
         })
     }
 }
 
-impl<'a> Iterator for DecoratedParser<'a> {
+impl<'a, I> Iterator for DecoratedParser<'a, I>
+where
+    I: Iterator<Item = Event<'a>>,
+{
     type Item = Event<'a>;
 
     fn next(&mut self) -> Option<Event<'a>> {
@@ -183,7 +286,12 @@ impl<'a> Iterator for DecoratedParser<'a> {
                     pulldown_cmark::CodeBlockKind::Indented => "",
                     pulldown_cmark::CodeBlockKind::Fenced(ref tag) => tag.as_ref(),
                 };
-                self.lang = tag.split(' ').map(|s| s.to_owned()).next();
+                let (lang, options) = CodeOptions::parse_info(tag).unwrap_or_else(|err| {
+                    log::warn!("Ignoring code block options: {err}");
+                    (tag.split([' ', ',']).next(), CodeOptions::default())
+                });
+                self.lang = lang.map(|s| s.to_owned());
+                self.options = options;
                 self.code = Some(vec![]);
                 Some(Text(pulldown_cmark::CowStr::Borrowed("")))
             }
@@ -194,6 +302,7 @@ impl<'a> Iterator for DecoratedParser<'a> {
                 } else {
                     self.syntax.format("", self.lang.as_deref(), self.theme)
                 };
+                let html = std::mem::take(&mut self.options).decorate(html);
                 // reset highlighter
                 self.lang = None;
                 self.code = None;
@@ -205,11 +314,14 @@ impl<'a> Iterator for DecoratedParser<'a> {
     }
 }
 
-pub(crate) fn decorate_markdown<'a>(
-    parser: cmark::Parser<'a>,
+pub(crate) fn decorate_markdown<'a, I>(
+    parser: I,
     syntax: std::sync::Arc<SyntaxHighlight>,
     theme_name: Option<&'a str>,
-) -> error::Result<DecoratedParser<'a>> {
+) -> error::Result<DecoratedParser<'a, I>>
+where
+    I: Iterator<Item = Event<'a>>,
+{
     DecoratedParser::new(parser, syntax, theme_name)
 }
 
@@ -288,6 +400,111 @@ mod test_syntsx {
 
         assert_data_eq!(&buf, expected.raw());
     }
+
+    #[test]
+    fn highlight_block_numbers_lines() {
+        let syntax = std::sync::Arc::new(SyntaxHighlight::new());
+        let highlight: Box<dyn liquid_core::ParseBlock> =
+            Box::new(CodeBlockParser::new(syntax, Some(classes_theme().into())).unwrap());
+        let parser = liquid::ParserBuilder::new()
+            .block(highlight)
+            .build()
+            .unwrap();
+        let template = parser
+            .parse("{% highlight rust linenos hl_lines=\"11-12\" start=10 %}let a = 1;\nlet b = a;\nlet c = b;\n{% endhighlight %}")
+            .unwrap();
+        let output = template.render(&liquid::Object::new());
+        let expected = str![[r#"
+<pre class="language-rust highlighter-syntect"><code class="highlight"><span class="line"><span class="lineno">10</span><span class="c-source c-rust"><span class="c-storage c-type c-rust">let</span> a <span class="c-keyword c-operator c-rust">=</span> <span class="c-constant c-numeric c-integer c-decimal c-rust">1</span><span class="c-punctuation c-terminator c-rust">;</span></span></span>
+<span class="line hl"><span class="lineno">11</span><span class="c-source c-rust"><span class="c-storage c-type c-rust">let</span> b <span class="c-keyword c-operator c-rust">=</span> a<span class="c-punctuation c-terminator c-rust">;</span></span></span>
+<span class="line hl"><span class="lineno">12</span><span class="c-source c-rust"><span class="c-storage c-type c-rust">let</span> c <span class="c-keyword c-operator c-rust">=</span> b<span class="c-punctuation c-terminator c-rust">;</span></span></span>
+</code></pre>
+"#]];
+
+        assert_data_eq!(output.unwrap(), expected.raw());
+
+        let template = parser
+            .parse("{% highlight rust,hl_lines=\"3-5 7\" %}{% endhighlight %}")
+            .unwrap();
+        assert!(template.render(&liquid::Object::new()).is_ok());
+        assert!(
+            parser
+                .parse("{% highlight rust,no_run %}{% endhighlight %}")
+                .is_ok()
+        );
+        assert!(
+            parser
+                .parse("{% highlight rust,hl_lines=a-3 %}{% endhighlight %}")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn markdown_numbers_lines() {
+        let mut buf = String::new();
+        let parser = cmark::Parser::new("```rust,linenos,hl_lines=2\nlet a = 1;\nlet b = a;\n```");
+        let syntax = std::sync::Arc::new(SyntaxHighlight::new());
+        cmark::html::push_html(
+            &mut buf,
+            decorate_markdown(parser, syntax, Some("base16-ocean.dark")).unwrap(),
+        );
+        let expected = str![[r#"
+<pre style="background-color:#2b303b;">
+<code><span class="line"><span class="lineno">1</span><span style="color:#b48ead;">let</span><span style="color:#c0c5ce;"> a = </span><span style="color:#d08770;">1</span><span style="color:#c0c5ce;">;</span></span>
+<span class="line hl"><span class="lineno">2</span><span style="color:#c0c5ce;"></span><span style="color:#b48ead;">let</span><span style="color:#c0c5ce;"> b = a;</span></span>
+</code></pre>
+
+"#]];
+
+        assert_data_eq!(&buf, expected.raw());
+    }
+
+    #[test]
+    fn markdown_renders_classes() {
+        let mut buf = String::new();
+        let parser = cmark::Parser::new("```rust\nfn main() {}\n```");
+        let syntax = std::sync::Arc::new(SyntaxHighlight::new());
+        cmark::html::push_html(
+            &mut buf,
+            decorate_markdown(parser, syntax, Some(classes_theme())).unwrap(),
+        );
+        assert!(
+            buf.starts_with(
+                "<pre class=\"language-rust highlighter-syntect\"><code class=\"highlight\">"
+            ),
+            "{buf}"
+        );
+        assert!(buf.contains("<span class=\"c-storage c-type c-function c-rust\">fn</span>"));
+        assert!(!buf.contains("style="));
+    }
+
+    #[test]
+    fn stylesheet_scopes_media() {
+        let syntax = SyntaxHighlight::new();
+        let themes = [
+            cobalt_config::StylesheetTheme {
+                theme: "InspiredGitHub".into(),
+                media: None,
+            },
+            cobalt_config::StylesheetTheme {
+                theme: "base16-ocean.dark".into(),
+                media: Some("(prefers-color-scheme: dark)".into()),
+            },
+        ];
+        let css = stylesheet(&syntax, &themes).unwrap();
+        let (light, dark) = css
+            .split_once("@media (prefers-color-scheme: dark) {\n")
+            .unwrap();
+        assert!(light.contains(".c-code {"), "{light}");
+        assert!(dark.contains(".c-code {"), "{dark}");
+        assert!(dark.ends_with("}\n"));
+
+        let unknown = [cobalt_config::StylesheetTheme {
+            theme: "missing".into(),
+            media: None,
+        }];
+        assert!(stylesheet(&syntax, &unknown).is_err());
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,72 @@
+/// No highlighting, defer to frontend
+#[cfg(not(feature = "syntax-highlight"))]
+#[derive(Default, Debug, Clone)]
+#[non_exhaustive]
+pub struct Raw {}
+
+#[cfg(not(feature = "syntax-highlight"))]
+impl Raw {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn has_theme(&self, _name: &str) -> bool {
+        false
+    }
+
+    pub fn themes(&self) -> impl Iterator<Item = String> + '_ {
+        std::iter::empty()
+    }
+
+    pub fn syntaxes(&self) -> impl Iterator<Item = String> + '_ {
+        std::iter::empty()
+    }
+
+    pub fn format(&self, code: &str, lang: Option<&str>, _theme: Option<&str>) -> String {
+        format(code, lang)
+    }
+}
+
+/// Escape `code` into a `<pre>`, tagging the language for frontend highlighters
+pub(crate) fn format(code: &str, lang: Option<&str>) -> String {
+    let code = escape(code);
+    match lang {
+        Some(lang) => format!("<pre><code class=\"language-{lang}\">{code}</code></pre>\n"),
+        None => format!("<pre><code>{code}</code></pre>\n"),
+    }
+}
+
+fn escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&#39;"),
+            '"' => escaped.push_str("&quot;"),
+            '&' => escaped.push_str("&amp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escapes_code() {
+        let output = format("fn f<'a>() -> &'a str { \"x\" }", Some("rust"));
+        assert_eq!(
+            output,
+            "<pre><code class=\"language-rust\">fn f&lt;&#39;a&gt;() -&gt; &amp;&#39;a str { &quot;x&quot; }</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn without_language() {
+        let output = format("<hello>", None);
+        assert_eq!(output, "<pre><code>&lt;hello&gt;</code></pre>\n");
+    }
+}
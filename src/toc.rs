@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+
+use liquid::model::Value;
+use pulldown_cmark as cmark;
+use pulldown_cmark::CowStr;
+use pulldown_cmark::Event;
+
+use crate::cobalt_model::slug;
+
+/// A heading found while rendering Markdown
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Heading {
+    pub(crate) level: u8,
+    pub(crate) id: String,
+    pub(crate) title: String,
+}
+
+/// Gives every heading a unique `id`, optionally followed by a self-link, recording the headings
+/// as they go by
+pub(crate) struct HeadingIds<'a, I> {
+    events: I,
+    anchors: bool,
+    ids: HashSet<String>,
+    pending: std::collections::VecDeque<Event<'a>>,
+    headings: Vec<Heading>,
+}
+
+impl<'a, I> HeadingIds<'a, I>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    pub(crate) fn new(events: I, anchors: bool) -> Self {
+        Self {
+            events,
+            anchors,
+            ids: Default::default(),
+            pending: Default::default(),
+            headings: Default::default(),
+        }
+    }
+
+    pub(crate) fn into_headings(self) -> Vec<Heading> {
+        self.headings
+    }
+
+    fn unique_id(&mut self, base: String) -> String {
+        let mut id = base.clone();
+        let mut i = 1;
+        while self.ids.contains(&id) {
+            id = format!("{base}-{i}");
+            i += 1;
+        }
+        self.ids.insert(id.clone());
+        id
+    }
+
+    fn heading(
+        &mut self,
+        level: cmark::HeadingLevel,
+        id: Option<CowStr<'a>>,
+        classes: Vec<CowStr<'a>>,
+        attrs: Vec<(CowStr<'a>, Option<CowStr<'a>>)>,
+    ) -> Event<'a> {
+        let mut content = Vec::new();
+        let mut title = String::new();
+        for event in self.events.by_ref() {
+            match &event {
+                Event::End(cmark::TagEnd::Heading(_)) => break,
+                Event::Text(text) | Event::Code(text) => title.push_str(text),
+                Event::InlineMath(text) | Event::DisplayMath(text) => title.push_str(text),
+                Event::SoftBreak | Event::HardBreak => title.push(' '),
+                _ => {}
+            }
+            content.push(event);
+        }
+
+        let base = match id {
+            Some(id) => id.to_string(),
+            None => {
+                let base = slug::slugify(&title);
+                if base.is_empty() {
+                    "section".to_owned()
+                } else {
+                    base
+                }
+            }
+        };
+        let id = self.unique_id(base);
+        if self.anchors {
+            content.push(Event::InlineHtml(
+                format!(
+                    r##" <a class="anchor" href="#{}" aria-hidden="true">#</a>"##,
+                    escape_attr(&id)
+                )
+                .into(),
+            ));
+        }
+        content.push(Event::End(cmark::TagEnd::Heading(level)));
+        self.pending.extend(content);
+
+        self.headings.push(Heading {
+            level: level as u8,
+            id: id.clone(),
+            title: title.trim().to_owned(),
+        });
+        Event::Start(cmark::Tag::Heading {
+            level,
+            id: Some(id.into()),
+            classes,
+            attrs,
+        })
+    }
+}
+
+impl<'a, I> Iterator for HeadingIds<'a, I>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+        match self.events.next() {
+            Some(Event::Start(cmark::Tag::Heading {
+                level,
+                id,
+                classes,
+                attrs,
+            })) => Some(self.heading(level, id, classes, attrs)),
+            item => item,
+        }
+    }
+}
+
+pub(crate) fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Nest `headings` within `min_level..=max_level` by level, for use as `page.toc`
+pub(crate) fn to_value(headings: &[Heading], min_level: u8, max_level: u8) -> Value {
+    let headings: Vec<_> = headings
+        .iter()
+        .filter(|h| min_level <= h.level && h.level <= max_level)
+        .collect();
+    Value::Array(nest(&mut headings.into_iter().peekable(), 0))
+}
+
+fn nest<'h>(
+    headings: &mut std::iter::Peekable<impl Iterator<Item = &'h Heading>>,
+    parent_level: u8,
+) -> Vec<Value> {
+    let mut entries = Vec::new();
+    while let Some(heading) = headings.next_if(|h| parent_level < h.level) {
+        let children = nest(headings, heading.level);
+        let entry: liquid::Object = [
+            ("level".into(), Value::scalar(i64::from(heading.level))),
+            ("id".into(), Value::scalar(heading.id.clone())),
+            ("title".into(), Value::scalar(heading.title.clone())),
+            ("children".into(), Value::Array(children)),
+        ]
+        .into_iter()
+        .collect();
+        entries.push(Value::Object(entry));
+    }
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(content: &str, anchors: bool) -> (String, Vec<Heading>) {
+        let parser = cmark::Parser::new_ext(content, cmark::Options::ENABLE_HEADING_ATTRIBUTES);
+        let mut headings = HeadingIds::new(parser, anchors);
+        let mut html = String::new();
+        cmark::html::push_html(&mut html, headings.by_ref());
+        (html, headings.into_headings())
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        let (html, headings) = render("# Intro\n## Intro\n## `code` & more\n", false);
+        assert_eq!(
+            html,
+            "<h1 id=\"intro\">Intro</h1>\n<h2 id=\"intro-1\">Intro</h2>\n<h2 id=\"code-more\"><code>code</code> &amp; more</h2>\n"
+        );
+        assert_eq!(headings[2].title, "code & more");
+    }
+
+    #[test]
+    fn explicit_ids_are_kept() {
+        let (html, _) = render("# Intro {#start}\n# Start\n", false);
+        assert_eq!(
+            html,
+            "<h1 id=\"start\">Intro</h1>\n<h1 id=\"start-1\">Start</h1>\n"
+        );
+    }
+
+    #[test]
+    fn explicit_ids_are_unique() {
+        let (html, _) = render("# Start\n# Intro {#start}\n# Outro {#start}\n", false);
+        assert_eq!(
+            html,
+            "<h1 id=\"start\">Start</h1>\n<h1 id=\"start-1\">Intro</h1>\n<h1 id=\"start-2\">Outro</h1>\n"
+        );
+    }
+
+    #[test]
+    fn anchors() {
+        let (html, _) = render("## Setup\n", true);
+        assert_eq!(
+            html,
+            "<h2 id=\"setup\">Setup <a class=\"anchor\" href=\"#setup\" aria-hidden=\"true\">#</a></h2>\n"
+        );
+    }
+
+    #[test]
+    fn toc_nests_by_level() {
+        let (_, headings) = render("# Title\n## A\n### A.1\n#### Deep\n## B\n", false);
+        let toc = to_value(&headings, 2, 3);
+        let expected = liquid::model::value!([
+            {
+                "level": 2, "id": "a", "title": "A",
+                "children": [{ "level": 3, "id": "a-1", "title": "A.1", "children": [] }]
+            },
+            { "level": 2, "id": "b", "title": "B", "children": [] }
+        ]);
+        assert_eq!(toc, expected);
+    }
+}
@@ -12,6 +12,8 @@ fn cli_tests() {
         t.skip("tests/cmd/syntax_highlighting.md");
         t.skip("tests/cmd/syntax_highlighting_disabled.md");
         t.skip("tests/cmd/syntax_highlighting_css.md");
+        t.skip("tests/cmd/highlight_classes.md");
+        t.skip("tests/cmd/custom_theme.md");
     }
     #[cfg(not(feature = "serve"))]
     {
@@ -21,9 +23,27 @@ fn cli_tests() {
     {
         t.skip("tests/cmd/sass.md");
         t.skip("tests/cmd/sass_custom_config.md");
+        t.skip("tests/cmd/sass_load_paths.md");
+    }
+    #[cfg(not(feature = "css-transform"))]
+    {
+        t.skip("tests/cmd/css_transform.md");
+    }
+    #[cfg(not(all(feature = "djot", feature = "org")))]
+    {
+        t.skip("tests/cmd/source_formats.md");
+    }
+    #[cfg(not(feature = "math"))]
+    {
+        t.skip("tests/cmd/math.md");
     }
     #[cfg(not(feature = "html-minifier"))]
     {
         t.skip("tests/cmd/example_minified.md");
+        t.skip("tests/cmd/asset_bundles.md");
+    }
+    #[cfg(not(feature = "compress"))]
+    {
+        t.skip("tests/cmd/compressed_outputs.md");
     }
 }
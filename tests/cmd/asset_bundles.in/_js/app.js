@@ -0,0 +1,2 @@
+// Runs once the page loads
+document.title = greet("bundles");
@@ -0,0 +1 @@
+function greet(name){return"Hello, "+name}document.title=greet("bundles")
\ No newline at end of file
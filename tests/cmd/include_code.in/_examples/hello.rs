@@ -0,0 +1,12 @@
+use std::env;
+
+// ANCHOR: greet
+fn greet(name: &str) -> String {
+    format!("Hello, {name}!")
+}
+// ANCHOR_END: greet
+
+fn main() {
+    let name = env::args().nth(1).unwrap_or_else(|| "world".to_owned());
+    println!("{}", greet(&name));
+}